@@ -0,0 +1,4 @@
+//* Migrations are embedded in the binary, so it has to be rebuilt when they change */
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
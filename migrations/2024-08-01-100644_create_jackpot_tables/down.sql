@@ -0,0 +1,5 @@
+-- The two tables reference each other, so the constraint has to go first
+ALTER TABLE JackpotPlayers DROP CONSTRAINT fk_session;
+
+DROP TABLE JackpotGames;
+DROP TABLE JackpotPlayers;
@@ -19,4 +19,4 @@ CREATE TABLE JackpotGames (
 -- Create the foreign key constraint in JackpotPlayers table
 ALTER TABLE JackpotPlayers
 ADD CONSTRAINT fk_session
-FOREIGN KEY ( ) REFERENCES JackpotGames(game_id);
+FOREIGN KEY (session_id) REFERENCES JackpotGames(game_id);
@@ -0,0 +1,5 @@
+DROP TABLE ledger_entries;
+
+ALTER TABLE users DROP COLUMN role;
+ALTER TABLE users ALTER COLUMN balance TYPE INTEGER USING balance::INTEGER;
+ALTER TABLE users ALTER COLUMN balance SET DEFAULT 0;
@@ -0,0 +1,15 @@
+ALTER TABLE users ALTER COLUMN balance TYPE DOUBLE PRECISION;
+ALTER TABLE users ALTER COLUMN balance SET DEFAULT 0;
+ALTER TABLE users ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user';
+
+CREATE TABLE ledger_entries (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    amount DOUBLE PRECISION NOT NULL,
+    balance_after DOUBLE PRECISION NOT NULL,
+    reason VARCHAR(50) NOT NULL,
+    reference VARCHAR(255),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX ledger_entries_user_id_idx ON ledger_entries (user_id);
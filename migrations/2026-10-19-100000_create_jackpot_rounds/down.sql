@@ -0,0 +1 @@
+DROP TABLE jackpot_rounds;
@@ -0,0 +1,13 @@
+CREATE TABLE jackpot_rounds (
+    id SERIAL PRIMARY KEY,
+    round_id VARCHAR(36) NOT NULL UNIQUE,
+    winner_id INTEGER NOT NULL REFERENCES users(id),
+    winner_name VARCHAR(255) NOT NULL,
+    pot DOUBLE PRECISION NOT NULL,
+    house_fee DOUBLE PRECISION NOT NULL,
+    payout DOUBLE PRECISION NOT NULL,
+    winning_ticket BIGINT NOT NULL,
+    total_tickets BIGINT NOT NULL,
+    player_count INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
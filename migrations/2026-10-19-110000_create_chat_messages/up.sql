@@ -0,0 +1,10 @@
+CREATE TABLE chat_messages (
+    id SERIAL PRIMARY KEY,
+    message_id VARCHAR(36) NOT NULL UNIQUE,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    username VARCHAR(255) NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX chat_messages_created_at_idx ON chat_messages (created_at);
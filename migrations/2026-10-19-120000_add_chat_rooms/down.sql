@@ -0,0 +1,3 @@
+DROP INDEX chat_messages_room_id_idx;
+
+ALTER TABLE chat_messages DROP COLUMN room;
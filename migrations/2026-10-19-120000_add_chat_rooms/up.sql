@@ -0,0 +1,3 @@
+ALTER TABLE chat_messages ADD COLUMN room VARCHAR(100) NOT NULL DEFAULT 'global';
+
+CREATE INDEX chat_messages_room_id_idx ON chat_messages (room, id);
@@ -0,0 +1,3 @@
+DROP TABLE chat_moderation_actions;
+
+ALTER TABLE chat_messages DROP COLUMN deleted_at;
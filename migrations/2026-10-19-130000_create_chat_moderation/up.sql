@@ -0,0 +1,16 @@
+ALTER TABLE chat_messages ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE TABLE chat_moderation_actions (
+    id SERIAL PRIMARY KEY,
+    action VARCHAR(20) NOT NULL,
+    moderator_id INT NOT NULL REFERENCES users(id),
+    target_user_id INT REFERENCES users(id),
+    room VARCHAR(100),
+    message_id VARCHAR(36),
+    duration_secs BIGINT,
+    reason TEXT,
+    expires_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX chat_moderation_actions_expires_at_idx ON chat_moderation_actions (expires_at);
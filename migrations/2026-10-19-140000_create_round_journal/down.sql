@@ -0,0 +1,2 @@
+DROP TABLE round_bets;
+DROP TABLE game_rounds;
@@ -0,0 +1,24 @@
+CREATE TABLE game_rounds (
+    id SERIAL PRIMARY KEY,
+    game VARCHAR(20) NOT NULL,
+    round_id VARCHAR(36) NOT NULL UNIQUE,
+    status VARCHAR(20) NOT NULL,
+    refunded DOUBLE PRECISION NOT NULL DEFAULT 0,
+    recovered_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX game_rounds_game_status_idx ON game_rounds (game, status);
+CREATE INDEX game_rounds_recovered_at_idx ON game_rounds (recovered_at);
+
+-- Anonymous crash and coinflip sockets bet with made up user ids, so there is no foreign key
+CREATE TABLE round_bets (
+    id SERIAL PRIMARY KEY,
+    round_id VARCHAR(36) NOT NULL,
+    user_id INT NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX round_bets_round_id_idx ON round_bets (round_id);
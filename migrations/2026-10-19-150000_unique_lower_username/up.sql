@@ -0,0 +1,3 @@
+-- Names differing only in case would pass for one another in chat, and ensure_house relies on it to insert the house once.
+-- Fails while such duplicates exist, they have to be renamed first.
+CREATE UNIQUE INDEX users_username_lower_idx ON users (lower(username));
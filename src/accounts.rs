@@ -0,0 +1,61 @@
+use actix_web::web;
+use bcrypt::{hash, verify, DEFAULT_COST};
+
+use crate::{
+    errors::auth::{GetUserError, LoginError, RegisterError},
+    models::user::{NewUser, User, NO_PASSWORD},
+    repositories::users::UserRepository,
+    validation::{validate_generic, validate_username},
+};
+
+//* bcrypt is slow on purpose, so it runs on the blocking pool without holding a database connection */
+pub async fn register(
+    users: &dyn UserRepository,
+    username: String,
+    password: String,
+    role: &str,
+) -> Result<User, RegisterError> {
+    if !validate_username(&username) || !validate_generic(&password) {
+        return Err(RegisterError::ForbiddenFormat);
+    }
+    let hashed_password = web::block(move || hash(password, DEFAULT_COST))
+        .await
+        .map_err(|_| RegisterError::InternalError)?
+        .map_err(|_| RegisterError::InternalError)?;
+
+    users
+        .create(NewUser {
+            username,
+            hashed_password,
+            balance: 0.0,
+            role: role.to_string(),
+        })
+        .await
+}
+
+pub async fn login(
+    users: &dyn UserRepository,
+    username: String,
+    password: String,
+) -> Result<User, LoginError> {
+    let user = match users.find_by_username(username).await {
+        Ok(user) => user,
+        Err(GetUserError::UserNotFound) => return Err(LoginError::InvalidCredentials),
+        Err(GetUserError::Unavailable(e)) => return Err(LoginError::Unavailable(e)),
+        Err(_) => return Err(LoginError::InternalError),
+    };
+    if user.hashed_password == NO_PASSWORD {
+        return Err(LoginError::InvalidCredentials);
+    }
+    let hashed_password = user.hashed_password.clone();
+    let valid = web::block(move || verify(password, &hashed_password))
+        .await
+        .map_err(|_| LoginError::InternalError)?
+        .map_err(|_| LoginError::InternalError)?;
+
+    if valid {
+        Ok(user)
+    } else {
+        Err(LoginError::InvalidCredentials)
+    }
+}
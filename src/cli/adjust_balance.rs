@@ -0,0 +1,49 @@
+use crate::{
+    errors::cli::CliError,
+    models::wallet::REASON_ADMIN_ADJUSTMENT,
+    repositories::{ledger::ApplyLedgerEntry, Repositories},
+};
+
+pub async fn run(
+    repos: &Repositories,
+    user: String,
+    by_id: bool,
+    amount: f64,
+    reason: String,
+) -> Result<(), CliError> {
+    if !amount.is_finite() || amount == 0.0 {
+        return Err(CliError::InvalidArgument(
+            "amount must be a non-zero number".to_string(),
+        ));
+    }
+    let reason = reason.trim().to_string();
+    if reason.is_empty() || reason.len() > 255 {
+        return Err(CliError::InvalidArgument(
+            "reason must be between 1 and 255 characters".to_string(),
+        ));
+    }
+
+    let user = if by_id {
+        let user_id = user
+            .parse()
+            .map_err(|_| CliError::InvalidArgument(format!("{user} is not a user id")))?;
+        repos.users.find(user_id).await?
+    } else {
+        repos.users.find_by_username(user).await?
+    };
+    //* The free text goes in the reference so the reason column stays a fixed set */
+    let entry = repos
+        .ledger
+        .apply(ApplyLedgerEntry {
+            user_id: user.id,
+            amount,
+            reason: REASON_ADMIN_ADJUSTMENT.to_string(),
+            reference: Some(reason),
+        })
+        .await?;
+    println!(
+        "Adjusted {} by {:.2}, balance is now {:.2} (ledger entry {})",
+        user.username, amount, entry.balance_after, entry.id
+    );
+    Ok(())
+}
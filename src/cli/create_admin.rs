@@ -0,0 +1,26 @@
+use std::io;
+
+use crate::{
+    accounts, errors::cli::CliError, models::user::ROLE_ADMIN, repositories::Repositories,
+};
+
+pub async fn run(
+    repos: &Repositories,
+    username: String,
+    password: Option<String>,
+) -> Result<(), CliError> {
+    //* Kept off the command line by default so it does not end up in shell history */
+    let password = match password {
+        Some(password) => password,
+        None => read_password()?,
+    };
+    let user = accounts::register(repos.users.as_ref(), username, password, ROLE_ADMIN).await?;
+    println!("Created admin {} with id {}", user.username, user.id);
+    Ok(())
+}
+
+fn read_password() -> io::Result<String> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
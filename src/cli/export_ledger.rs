@@ -0,0 +1,68 @@
+use std::{
+    borrow::Cow,
+    io::{self, BufWriter, Write},
+};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::{errors::cli::CliError, models::wallet::LedgerEntry, repositories::Repositories};
+
+const PAGE_SIZE: i64 = 1000;
+
+pub async fn run(
+    repos: &Repositories,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+) -> Result<(), CliError> {
+    let from = from.unwrap_or(DateTime::UNIX_EPOCH.naive_utc());
+    let to = to.unwrap_or_else(|| Utc::now().naive_utc());
+    if from >= to {
+        return Err(CliError::InvalidArgument(
+            "--from must be before --to".to_string(),
+        ));
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(
+        out,
+        "id,user_id,amount,balance_after,reason,reference,created_at"
+    )?;
+    let (mut after_id, mut exported) = (0, 0);
+    loop {
+        let page = repos.ledger.entries(from, to, after_id, PAGE_SIZE).await?;
+        for entry in &page {
+            write_row(&mut out, entry)?;
+        }
+        exported += page.len();
+        match page.last() {
+            Some(last) if page.len() as i64 == PAGE_SIZE => after_id = last.id,
+            _ => break,
+        }
+    }
+    out.flush()?;
+    tracing::info!(entries = exported, %from, %to, "Ledger exported");
+    Ok(())
+}
+
+fn write_row(out: &mut impl Write, entry: &LedgerEntry) -> io::Result<()> {
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{}",
+        entry.id,
+        entry.user_id,
+        entry.amount,
+        entry.balance_after,
+        csv_field(&entry.reason),
+        csv_field(entry.reference.as_deref().unwrap_or("")),
+        entry.created_at.format("%Y-%m-%dT%H:%M:%S%.f"),
+    )
+}
+
+//* References can be free text from adjust-balance, so they are quoted when needed */
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
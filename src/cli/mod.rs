@@ -0,0 +1,103 @@
+mod adjust_balance;
+mod create_admin;
+mod export_ledger;
+mod verify_round;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use clap::{Parser, Subcommand};
+
+use crate::{
+    errors::cli::CliError,
+    events::{GAME_COINFLIP, GAME_CRASH, GAME_JACKPOT},
+    repositories::Repositories,
+};
+
+//* Admin commands go through the same repositories as the server, so they keep its rules */
+#[derive(Parser)]
+#[command(version, about = "Jackpot, coinflip and crash game server")]
+pub struct Cli {
+    //* Serves when left out */
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP and websocket server
+    Serve,
+    /// Apply pending database migrations and exit
+    Migrate,
+    /// Register a user with the admin role
+    CreateAdmin {
+        username: String,
+        /// Read from the first line of stdin when not given
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Credit (positive amount) or debit (negative amount) a user through the ledger
+    AdjustBalance {
+        /// Username, or user id with --by-id
+        user: String,
+        /// Negative to debit
+        #[arg(allow_negative_numbers = true)]
+        amount: f64,
+        /// Why the balance was adjusted, kept on the ledger entry
+        #[arg(long)]
+        reason: String,
+        /// Look the user up by id instead of username
+        #[arg(long)]
+        by_id: bool,
+    },
+    /// Check a round's journal against the ledger and the round history
+    VerifyRound {
+        #[arg(value_parser = [GAME_JACKPOT, GAME_COINFLIP, GAME_CRASH])]
+        game: String,
+        round_id: String,
+    },
+    /// Write ledger entries to stdout as CSV
+    ExportLedger {
+        /// Inclusive, YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS, the beginning of time by default
+        #[arg(long, value_parser = parse_timestamp)]
+        from: Option<NaiveDateTime>,
+        /// Exclusive, same format as --from, now by default
+        #[arg(long, value_parser = parse_timestamp)]
+        to: Option<NaiveDateTime>,
+    },
+}
+
+impl Command {
+    //* Only the server and `migrate` may change the schema, admin commands just refuse to run on an old one */
+    pub fn runs_migrations(&self, auto_migrate: bool) -> bool {
+        match self {
+            Command::Serve => auto_migrate,
+            Command::Migrate => true,
+            _ => false,
+        }
+    }
+}
+
+pub async fn run(command: Command, repos: Repositories) -> Result<(), CliError> {
+    match command {
+        //* Both are handled by main */
+        Command::Serve | Command::Migrate => Ok(()),
+        Command::CreateAdmin { username, password } => {
+            create_admin::run(&repos, username, password).await
+        }
+        Command::AdjustBalance {
+            user,
+            amount,
+            reason,
+            by_id,
+        } => adjust_balance::run(&repos, user, by_id, amount, reason).await,
+        Command::VerifyRound { game, round_id } => verify_round::run(&repos, game, round_id).await,
+        Command::ExportLedger { from, to } => export_ledger::run(&repos, from, to).await,
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .map_err(|_| format!("expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS, got {value}"))
+}
@@ -0,0 +1,19 @@
+use std::io;
+
+use crate::{errors::cli::CliError, repositories::Repositories};
+
+pub async fn run(repos: &Repositories, game: String, round_id: String) -> Result<(), CliError> {
+    let audit = repos.rounds.verify(game, round_id).await?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&audit).map_err(io::Error::from)?
+    );
+    if audit.issues.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::RoundMismatch(
+            audit.round.round_id,
+            audit.issues.len(),
+        ))
+    }
+}
@@ -0,0 +1,945 @@
+use std::{env, fs, io, str::FromStr, time::Duration};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
+
+use crate::{errors::config::ConfigError, rate_limit::RateLimit, validation::validate_username};
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+//* Comma separated values, trimmed and lowercased */
+pub fn env_list(key: &str, default: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_else(|_| default.to_string())
+        .split(',')
+        .map(|val| val.trim().to_lowercase())
+        .filter(|val| !val.is_empty())
+        .collect()
+}
+
+//* A value that is set but doesn't parse is an error rather than falling back */
+fn override_from_env<T: FromStr>(key: &str, value: &mut T) -> Result<(), ConfigError> {
+    if let Ok(val) = env::var(key) {
+        *value = val
+            .trim()
+            .parse()
+            .map_err(|_| ConfigError::InvalidEnv(key.to_string()))?;
+    }
+    Ok(())
+}
+
+//* An empty value clears the setting */
+fn override_option_from_env<T: FromStr>(
+    key: &str,
+    value: &mut Option<T>,
+) -> Result<(), ConfigError> {
+    if let Ok(val) = env::var(key) {
+        *value = match val.trim() {
+            "" => None,
+            val => Some(
+                val.parse()
+                    .map_err(|_| ConfigError::InvalidEnv(key.to_string()))?,
+            ),
+        };
+    }
+    Ok(())
+}
+
+//* Loaded from the TOML file in CONFIG_FILE (config.toml by default), then overridden by env */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub auth: AuthSettings,
+    pub database: DatabaseSettings,
+    pub jackpot: JackpotSettings,
+    pub crash: CrashSettings,
+    pub coinflip: CoinflipSettings,
+    pub chat: ChatSettings,
+    pub house: HouseSettings,
+    pub rate_limit: RateLimitSettings,
+    pub heartbeat: HeartbeatSettings,
+    pub shutdown: ShutdownSettings,
+    pub log: LogSettings,
+    pub metrics: MetricsSettings,
+    pub health: HealthSettings,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+    pub cors_origins: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub jwt_ttl_hours: i64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub backend: StorageBackend,
+    pub pool_size: u32,
+    //* How long a request waits for a free connection before it fails */
+    pub connection_timeout_ms: u64,
+    //* When off, startup refuses to run against a schema with pending migrations */
+    pub auto_migrate: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JackpotSettings {
+    pub countdown_secs: u64,
+    //* The first room is the default one for sockets that don't pick a room */
+    pub rooms: Vec<RoomSettings>,
+    //* Upper bound on the sum of one player's deposits within a single round */
+    pub max_round_deposit: f64,
+    pub max_players: usize,
+    //* A deposit landing with less than this much time left extends the countdown, zero disables it */
+    pub late_deposit_window_secs: u64,
+    pub late_deposit_extension_secs: u64,
+    pub max_extensions: u32,
+    //* Percentage of the pot kept by the house when a round resolves */
+    pub rake_percent: f64,
+    pub rake_cap: Option<f64>,
+    //* Pots smaller than this are paid out in full */
+    pub rake_min_pot: f64,
+}
+
+//* Written as name:min_deposit:max_deposit in JACKPOT_ROOMS */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoomSettings {
+    pub name: String,
+    pub min_deposit: f64,
+    pub max_deposit: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrashSettings {
+    pub tick_ms: u64,
+    //* Pause between a crash and the next round */
+    pub cooldown_secs: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoinflipSettings {
+    //* The house bot takes the other side of games nobody joined, it is off unless enabled */
+    pub house_bot: bool,
+    pub house_bot_delay_secs: u64,
+    pub house_bot_min_amount: f64,
+    pub house_bot_max_amount: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatSettings {
+    //* Most recent messages kept per room and replayed to everyone joining it */
+    pub history_size: usize,
+    pub languages: Vec<String>,
+    pub max_length: usize,
+    //* Masked as whole words, case insensitively */
+    pub banned_words: Vec<String>,
+    //* Accounts younger than this can not post links */
+    pub link_min_account_age_hours: i64,
+    //* Smallest tip, and smallest share of a rain */
+    pub min_transfer: f64,
+    //* Rain goes to users who posted in the room within this window */
+    pub rain_window_secs: u64,
+    pub rain_max_recipients: usize,
+    //* Jackpot and coinflip wins paying at least this much are announced in the global room */
+    pub announce_min_payout: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HouseSettings {
+    //* Account that takes the rake and backs the coinflip bot, the name can not be registered */
+    pub account_username: String,
+}
+
+//* Limits are written as "burst:per_second" */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSettings {
+    //* Header set by a trusted reverse proxy with the client IP, e.g. X-Forwarded-For */
+    pub trusted_proxy_header: Option<String>,
+    pub login: RateLimit,
+    pub register: RateLimit,
+    pub jackpot: JackpotRateLimits,
+    pub crash: CrashRateLimits,
+    pub coinflip: CoinflipRateLimits,
+    pub chat: ChatRateLimits,
+}
+
+//* `connection` counts every frame of a socket, the others are per user and command */
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JackpotRateLimits {
+    pub connection: RateLimit,
+    //* The socket is closed once it hits this many violations within a minute */
+    pub max_violations: usize,
+    pub deposit: RateLimit,
+    pub join_room: RateLimit,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrashRateLimits {
+    pub connection: RateLimit,
+    pub max_violations: usize,
+    pub deposit: RateLimit,
+    pub cashout: RateLimit,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoinflipRateLimits {
+    pub connection: RateLimit,
+    pub max_violations: usize,
+    pub create: RateLimit,
+    pub join: RateLimit,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatRateLimits {
+    pub connection: RateLimit,
+    pub max_violations: usize,
+    pub message: RateLimit,
+    //* Slash commands such as /tip and /rain */
+    pub command: RateLimit,
+    pub join: RateLimit,
+    pub moderate: RateLimit,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeartbeatSettings {
+    pub interval_secs: u64,
+    //* Sockets that haven't answered a ping for this long are dropped */
+    pub timeout_secs: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownSettings {
+    //* Time each game gets to settle its open rounds */
+    pub timeout_secs: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthSettings {
+    //* How long /readyz waits on the database and each game server */
+    pub readiness_timeout_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    //* Filter directives in the RUST_LOG syntax, e.g. "info,jackpot_rust=debug" */
+    pub level: String,
+    pub format: LogFormat,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    //* When set, /metrics requires "Authorization: Bearer <token>" */
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+//* Memory keeps nothing across restarts, it is meant for tests and local runs without Postgres */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Postgres,
+    Memory,
+}
+
+impl FromStr for StorageBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "postgres" => Ok(StorageBackend::Postgres),
+            "memory" => Ok(StorageBackend::Memory),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            host: "127.0.0.1".to_string(),
+            port: 8081,
+            cors_origins: vec!["http://127.0.0.1:5500".to_string()],
+        }
+    }
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        AuthSettings { jwt_ttl_hours: 48 }
+    }
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            backend: StorageBackend::Postgres,
+            pool_size: 10,
+            connection_timeout_ms: 5000,
+            auto_migrate: true,
+        }
+    }
+}
+
+impl Default for JackpotSettings {
+    fn default() -> Self {
+        let room = |name: &str, min_deposit, max_deposit| RoomSettings {
+            name: name.to_string(),
+            min_deposit,
+            max_deposit,
+        };
+        JackpotSettings {
+            countdown_secs: 15,
+            rooms: vec![
+                room("low", 0.1, 10.0),
+                room("medium", 10.0, 100.0),
+                room("high", 100.0, 1000.0),
+            ],
+            max_round_deposit: 5000.0,
+            max_players: 50,
+            late_deposit_window_secs: 0,
+            late_deposit_extension_secs: 5,
+            max_extensions: 3,
+            rake_percent: 5.0,
+            rake_cap: None,
+            rake_min_pot: 1.0,
+        }
+    }
+}
+
+impl Default for CrashSettings {
+    fn default() -> Self {
+        CrashSettings {
+            tick_ms: 200,
+            cooldown_secs: 15,
+        }
+    }
+}
+
+impl Default for CoinflipSettings {
+    fn default() -> Self {
+        CoinflipSettings {
+            house_bot: false,
+            house_bot_delay_secs: 20,
+            house_bot_min_amount: 1.0,
+            house_bot_max_amount: 100.0,
+        }
+    }
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        ChatSettings {
+            history_size: 50,
+            languages: ["en", "es", "de", "fr", "ru"].map(String::from).to_vec(),
+            max_length: 500,
+            banned_words: Vec::new(),
+            link_min_account_age_hours: 24,
+            min_transfer: 0.01,
+            rain_window_secs: 600,
+            rain_max_recipients: 20,
+            announce_min_payout: 100.0,
+        }
+    }
+}
+
+impl Default for HouseSettings {
+    fn default() -> Self {
+        HouseSettings {
+            account_username: "house".to_string(),
+        }
+    }
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        RateLimitSettings {
+            trusted_proxy_header: None,
+            login: RateLimit::new(5.0, 0.1),
+            register: RateLimit::new(3.0, 0.01),
+            jackpot: JackpotRateLimits::default(),
+            crash: CrashRateLimits::default(),
+            coinflip: CoinflipRateLimits::default(),
+            chat: ChatRateLimits::default(),
+        }
+    }
+}
+
+impl Default for JackpotRateLimits {
+    fn default() -> Self {
+        JackpotRateLimits {
+            connection: RateLimit::new(10.0, 5.0),
+            max_violations: 10,
+            deposit: RateLimit::new(3.0, 1.0),
+            join_room: RateLimit::new(5.0, 1.0),
+        }
+    }
+}
+
+impl Default for CrashRateLimits {
+    fn default() -> Self {
+        CrashRateLimits {
+            connection: RateLimit::new(10.0, 5.0),
+            max_violations: 10,
+            deposit: RateLimit::new(3.0, 1.0),
+            cashout: RateLimit::new(3.0, 1.0),
+        }
+    }
+}
+
+impl Default for CoinflipRateLimits {
+    fn default() -> Self {
+        CoinflipRateLimits {
+            connection: RateLimit::new(10.0, 5.0),
+            max_violations: 10,
+            create: RateLimit::new(2.0, 0.5),
+            join: RateLimit::new(3.0, 1.0),
+        }
+    }
+}
+
+impl Default for ChatRateLimits {
+    fn default() -> Self {
+        ChatRateLimits {
+            connection: RateLimit::new(10.0, 3.0),
+            max_violations: 10,
+            message: RateLimit::new(5.0, 1.0),
+            command: RateLimit::new(2.0, 0.2),
+            join: RateLimit::new(5.0, 1.0),
+            moderate: RateLimit::new(10.0, 2.0),
+        }
+    }
+}
+
+impl Default for HeartbeatSettings {
+    fn default() -> Self {
+        HeartbeatSettings {
+            interval_secs: 5,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        ShutdownSettings { timeout_secs: 60 }
+    }
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        HealthSettings {
+            readiness_timeout_ms: 2000,
+        }
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = env::var("CONFIG_FILE").ok();
+        let file = path.as_deref().unwrap_or(DEFAULT_CONFIG_FILE);
+        let mut settings = match fs::read_to_string(file) {
+            Ok(contents) => toml::from_str(&contents)?,
+            //* The default file is optional, an explicitly configured one is not */
+            Err(e) if e.kind() == io::ErrorKind::NotFound && path.is_none() => Settings::default(),
+            Err(e) => return Err(ConfigError::Read(file.to_string(), e)),
+        };
+        settings.apply_env()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        override_from_env("SERVER_HOST", &mut self.server.host)?;
+        override_from_env("SERVER_PORT", &mut self.server.port)?;
+        if env::var("CORS_ORIGINS").is_ok() {
+            self.server.cors_origins = env_list("CORS_ORIGINS", "");
+        }
+        override_from_env("JWT_TTL_HOURS", &mut self.auth.jwt_ttl_hours)?;
+        override_from_env("DB_BACKEND", &mut self.database.backend)?;
+        override_from_env("DB_POOL_SIZE", &mut self.database.pool_size)?;
+        override_from_env(
+            "DB_CONNECTION_TIMEOUT_MS",
+            &mut self.database.connection_timeout_ms,
+        )?;
+        override_from_env("DB_AUTO_MIGRATE", &mut self.database.auto_migrate)?;
+        self.apply_jackpot_env()?;
+        override_from_env("CRASH_TICK_MS", &mut self.crash.tick_ms)?;
+        override_from_env("CRASH_COOLDOWN_SECS", &mut self.crash.cooldown_secs)?;
+        override_from_env("COINFLIP_HOUSE_BOT", &mut self.coinflip.house_bot)?;
+        override_from_env(
+            "COINFLIP_HOUSE_BOT_DELAY_SECS",
+            &mut self.coinflip.house_bot_delay_secs,
+        )?;
+        override_from_env(
+            "COINFLIP_HOUSE_BOT_MIN_AMOUNT",
+            &mut self.coinflip.house_bot_min_amount,
+        )?;
+        override_from_env(
+            "COINFLIP_HOUSE_BOT_MAX_AMOUNT",
+            &mut self.coinflip.house_bot_max_amount,
+        )?;
+        self.apply_chat_env()?;
+        override_from_env("HOUSE_ACCOUNT_USERNAME", &mut self.house.account_username)?;
+        self.apply_rate_limit_env()?;
+        override_from_env(
+            "WS_HEARTBEAT_INTERVAL_SECS",
+            &mut self.heartbeat.interval_secs,
+        )?;
+        override_from_env(
+            "WS_HEARTBEAT_TIMEOUT_SECS",
+            &mut self.heartbeat.timeout_secs,
+        )?;
+        override_from_env("SHUTDOWN_TIMEOUT_SECS", &mut self.shutdown.timeout_secs)?;
+        override_from_env("LOG_LEVEL", &mut self.log.level)?;
+        override_from_env("LOG_FORMAT", &mut self.log.format)?;
+        override_from_env(
+            "READINESS_TIMEOUT_MS",
+            &mut self.health.readiness_timeout_ms,
+        )?;
+        if let Ok(token) = env::var("METRICS_TOKEN") {
+            self.metrics.token = Some(token).filter(|token| !token.is_empty());
+        }
+        Ok(())
+    }
+
+    fn apply_jackpot_env(&mut self) -> Result<(), ConfigError> {
+        let jackpot = &mut self.jackpot;
+        override_from_env("JACKPOT_COUNTDOWN_SECS", &mut jackpot.countdown_secs)?;
+        if let Ok(val) = env::var("JACKPOT_ROOMS") {
+            jackpot.rooms = val
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| ConfigError::InvalidEnv("JACKPOT_ROOMS".to_string()))?;
+        }
+        override_from_env("JACKPOT_MAX_ROUND_DEPOSIT", &mut jackpot.max_round_deposit)?;
+        override_from_env("JACKPOT_MAX_PLAYERS", &mut jackpot.max_players)?;
+        override_from_env(
+            "JACKPOT_LATE_DEPOSIT_WINDOW_SECS",
+            &mut jackpot.late_deposit_window_secs,
+        )?;
+        override_from_env(
+            "JACKPOT_LATE_DEPOSIT_EXTENSION_SECS",
+            &mut jackpot.late_deposit_extension_secs,
+        )?;
+        override_from_env("JACKPOT_MAX_EXTENSIONS", &mut jackpot.max_extensions)?;
+        override_from_env("JACKPOT_RAKE_PERCENT", &mut jackpot.rake_percent)?;
+        override_option_from_env("JACKPOT_RAKE_CAP", &mut jackpot.rake_cap)?;
+        override_from_env("JACKPOT_RAKE_MIN_POT", &mut jackpot.rake_min_pot)?;
+        Ok(())
+    }
+
+    fn apply_chat_env(&mut self) -> Result<(), ConfigError> {
+        let chat = &mut self.chat;
+        override_from_env("CHAT_HISTORY_SIZE", &mut chat.history_size)?;
+        if env::var("CHAT_LANGUAGES").is_ok() {
+            chat.languages = env_list("CHAT_LANGUAGES", "");
+        }
+        override_from_env("CHAT_MAX_LENGTH", &mut chat.max_length)?;
+        if env::var("CHAT_BANNED_WORDS").is_ok() {
+            chat.banned_words = env_list("CHAT_BANNED_WORDS", "");
+        }
+        override_from_env(
+            "CHAT_LINK_MIN_ACCOUNT_AGE_HOURS",
+            &mut chat.link_min_account_age_hours,
+        )?;
+        override_from_env("CHAT_MIN_TRANSFER", &mut chat.min_transfer)?;
+        override_from_env("CHAT_RAIN_WINDOW_SECS", &mut chat.rain_window_secs)?;
+        override_from_env("CHAT_RAIN_MAX_RECIPIENTS", &mut chat.rain_max_recipients)?;
+        override_from_env("CHAT_ANNOUNCE_MIN_PAYOUT", &mut chat.announce_min_payout)?;
+        Ok(())
+    }
+
+    //* GAME_RATE_LIMIT for the connection, GAME_RATE_LIMIT_<COMMAND> per command */
+    fn apply_rate_limit_env(&mut self) -> Result<(), ConfigError> {
+        let limits = &mut self.rate_limit;
+        override_option_from_env("TRUSTED_PROXY_HEADER", &mut limits.trusted_proxy_header)?;
+        override_from_env("LOGIN_RATE_LIMIT", &mut limits.login)?;
+        override_from_env("REGISTER_RATE_LIMIT", &mut limits.register)?;
+
+        let jackpot = &mut limits.jackpot;
+        override_from_env("JACKPOT_RATE_LIMIT", &mut jackpot.connection)?;
+        override_from_env(
+            "JACKPOT_RATE_LIMIT_MAX_VIOLATIONS",
+            &mut jackpot.max_violations,
+        )?;
+        override_from_env("JACKPOT_RATE_LIMIT_DEPOSIT", &mut jackpot.deposit)?;
+        override_from_env("JACKPOT_RATE_LIMIT_JOIN_ROOM", &mut jackpot.join_room)?;
+
+        let crash = &mut limits.crash;
+        override_from_env("CRASH_RATE_LIMIT", &mut crash.connection)?;
+        override_from_env("CRASH_RATE_LIMIT_MAX_VIOLATIONS", &mut crash.max_violations)?;
+        override_from_env("CRASH_RATE_LIMIT_DEPOSIT", &mut crash.deposit)?;
+        override_from_env("CRASH_RATE_LIMIT_CASHOUT", &mut crash.cashout)?;
+
+        let coinflip = &mut limits.coinflip;
+        override_from_env("COINFLIP_RATE_LIMIT", &mut coinflip.connection)?;
+        override_from_env(
+            "COINFLIP_RATE_LIMIT_MAX_VIOLATIONS",
+            &mut coinflip.max_violations,
+        )?;
+        override_from_env("COINFLIP_RATE_LIMIT_CREATE", &mut coinflip.create)?;
+        override_from_env("COINFLIP_RATE_LIMIT_JOIN", &mut coinflip.join)?;
+
+        let chat = &mut limits.chat;
+        override_from_env("CHAT_RATE_LIMIT", &mut chat.connection)?;
+        override_from_env("CHAT_RATE_LIMIT_MAX_VIOLATIONS", &mut chat.max_violations)?;
+        override_from_env("CHAT_RATE_LIMIT_MESSAGE", &mut chat.message)?;
+        override_from_env("CHAT_RATE_LIMIT_COMMAND", &mut chat.command)?;
+        override_from_env("CHAT_RATE_LIMIT_JOIN", &mut chat.join)?;
+        override_from_env("CHAT_RATE_LIMIT_MODERATE", &mut chat.moderate)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.host.is_empty() {
+            return Err(ConfigError::Invalid("server.host cannot be empty"));
+        }
+        if self.auth.jwt_ttl_hours <= 0 {
+            return Err(ConfigError::Invalid("auth.jwt_ttl_hours must be positive"));
+        }
+        if self.database.pool_size == 0 || self.database.connection_timeout_ms == 0 {
+            return Err(ConfigError::Invalid(
+                "database.pool_size and database.connection_timeout_ms must be positive",
+            ));
+        }
+        if self.jackpot.countdown_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "jackpot.countdown_secs must be positive",
+            ));
+        }
+        if self.jackpot.max_round_deposit <= 0.0 || self.jackpot.max_players < 2 {
+            return Err(ConfigError::Invalid(
+                "jackpot.max_round_deposit must be positive and jackpot.max_players at least 2",
+            ));
+        }
+        self.jackpot.validate()?;
+        if self.crash.tick_ms == 0 {
+            return Err(ConfigError::Invalid("crash.tick_ms must be positive"));
+        }
+        if self.coinflip.house_bot_min_amount <= 0.0
+            || self.coinflip.house_bot_max_amount < self.coinflip.house_bot_min_amount
+        {
+            return Err(ConfigError::Invalid(
+                "coinflip.house_bot_min_amount must be positive and at most house_bot_max_amount",
+            ));
+        }
+        if self.chat.max_length == 0 || self.chat.rain_max_recipients == 0 {
+            return Err(ConfigError::Invalid(
+                "chat.max_length and chat.rain_max_recipients must be positive",
+            ));
+        }
+        if self.chat.min_transfer <= 0.0 || self.chat.link_min_account_age_hours < 0 {
+            return Err(ConfigError::Invalid(
+                "chat.min_transfer must be positive and chat.link_min_account_age_hours not negative",
+            ));
+        }
+        if self.chat.word_filter().is_err() {
+            return Err(ConfigError::Invalid(
+                "chat.banned_words could not be compiled",
+            ));
+        }
+        if !validate_username(&self.house.account_username) {
+            return Err(ConfigError::Invalid(
+                "house.account_username is not a valid username",
+            ));
+        }
+        if self.heartbeat.interval_secs == 0
+            || self.heartbeat.timeout_secs <= self.heartbeat.interval_secs
+        {
+            return Err(ConfigError::Invalid(
+                "heartbeat.timeout_secs must be greater than heartbeat.interval_secs",
+            ));
+        }
+        if self.health.readiness_timeout_ms == 0 {
+            return Err(ConfigError::Invalid(
+                "health.readiness_timeout_ms must be positive",
+            ));
+        }
+        if EnvFilter::try_new(&self.log.level).is_err() {
+            return Err(ConfigError::Invalid("log.level is not a valid filter"));
+        }
+        Ok(())
+    }
+}
+
+impl AuthSettings {
+    pub fn jwt_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.jwt_ttl_hours)
+    }
+}
+
+impl JackpotSettings {
+    pub fn countdown(&self) -> Duration {
+        Duration::from_secs(self.countdown_secs)
+    }
+
+    pub fn late_deposit_window(&self) -> Duration {
+        Duration::from_secs(self.late_deposit_window_secs)
+    }
+
+    pub fn late_deposit_extension(&self) -> Duration {
+        Duration::from_secs(self.late_deposit_extension_secs)
+    }
+
+    //* The rake on a pot, rounded down to the cent and never more than the pot itself */
+    pub fn house_fee(&self, pot: f64) -> f64 {
+        if pot < self.rake_min_pot || self.rake_percent <= 0.0 {
+            return 0.0;
+        }
+        let fee = pot * self.rake_percent / 100.0;
+        let fee = match self.rake_cap {
+            Some(cap) => fee.min(cap),
+            None => fee,
+        };
+        ((fee * 100.0).floor() / 100.0).clamp(0.0, pot)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.rooms.is_empty() {
+            return Err(ConfigError::Invalid("jackpot.rooms cannot be empty"));
+        }
+        for (i, room) in self.rooms.iter().enumerate() {
+            if room.name.trim().is_empty() || room.min_deposit <= 0.0 {
+                return Err(ConfigError::Invalid(
+                    "jackpot.rooms need a name and a positive min_deposit",
+                ));
+            }
+            if room.min_deposit > room.max_deposit {
+                return Err(ConfigError::Invalid(
+                    "jackpot.rooms min_deposit can not be greater than max_deposit",
+                ));
+            }
+            if self.rooms[..i].iter().any(|other| other.name == room.name) {
+                return Err(ConfigError::Invalid("jackpot.rooms names must be unique"));
+            }
+        }
+        if !(0.0..=100.0).contains(&self.rake_percent) {
+            return Err(ConfigError::Invalid(
+                "jackpot.rake_percent must be between 0 and 100",
+            ));
+        }
+        if self.rake_cap.is_some_and(|cap| cap < 0.0) || self.rake_min_pot < 0.0 {
+            return Err(ConfigError::Invalid(
+                "jackpot.rake_cap and jackpot.rake_min_pot can not be negative",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RoomSettings {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(':');
+        let name = parts.next().ok_or(())?.trim();
+        let min_deposit = parts.next().ok_or(())?.trim().parse().map_err(|_| ())?;
+        let max_deposit = parts.next().ok_or(())?.trim().parse().map_err(|_| ())?;
+        if name.is_empty() || parts.next().is_some() {
+            return Err(());
+        }
+        Ok(RoomSettings {
+            name: name.to_string(),
+            min_deposit,
+            max_deposit,
+        })
+    }
+}
+
+impl CoinflipSettings {
+    pub fn house_bot_delay(&self) -> Duration {
+        Duration::from_secs(self.house_bot_delay_secs)
+    }
+}
+
+impl ChatSettings {
+    //* Matches any of the banned words as a whole word, None when there are none */
+    pub fn word_filter(&self) -> Result<Option<Regex>, regex::Error> {
+        if self.banned_words.is_empty() {
+            return Ok(None);
+        }
+        let words: Vec<String> = self.banned_words.iter().map(|w| regex::escape(w)).collect();
+        Regex::new(&format!(r"(?i)\b({})\b", words.join("|"))).map(Some)
+    }
+
+    pub fn link_min_account_age(&self) -> chrono::TimeDelta {
+        chrono::TimeDelta::hours(self.link_min_account_age_hours)
+    }
+
+    pub fn rain_window(&self) -> Duration {
+        Duration::from_secs(self.rain_window_secs)
+    }
+}
+
+impl CrashSettings {
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick_ms)
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown_secs)
+    }
+}
+
+impl DatabaseSettings {
+    pub fn connection_timeout(&self) -> Duration {
+        Duration::from_millis(self.connection_timeout_ms)
+    }
+}
+
+impl HeartbeatSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+impl HealthSettings {
+    pub fn readiness_timeout(&self) -> Duration {
+        Duration::from_millis(self.readiness_timeout_ms)
+    }
+}
+
+impl ShutdownSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rake(rake_percent: f64, rake_cap: Option<f64>, rake_min_pot: f64) -> JackpotSettings {
+        JackpotSettings {
+            rake_percent,
+            rake_cap,
+            rake_min_pot,
+            ..JackpotSettings::default()
+        }
+    }
+
+    #[test]
+    fn house_fee_is_rounded_down_and_capped() {
+        let settings = rake(5.0, Some(2.0), 1.0);
+        assert_eq!(settings.house_fee(0.5), 0.0);
+        assert_eq!(settings.house_fee(10.19), 0.5);
+        assert_eq!(settings.house_fee(1000.0), 2.0);
+    }
+
+    #[test]
+    fn no_rake_keeps_nothing() {
+        let settings = rake(0.0, None, 0.0);
+        assert_eq!(settings.house_fee(0.0), 0.0);
+        assert_eq!(settings.house_fee(123.45), 0.0);
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn full_rake_never_exceeds_the_pot() {
+        let settings = rake(100.0, None, 0.0);
+        assert!(settings.validate().is_ok());
+        for pot in [0.01, 0.1 + 0.2, 10.005, 999.99] {
+            let fee = settings.house_fee(pot);
+            assert!(fee <= pot, "fee {fee} for pot {pot}");
+            assert!(pot - fee < 0.01, "fee {fee} for pot {pot}");
+        }
+        assert!(rake(100.5, None, 0.0).validate().is_err());
+    }
+
+    #[test]
+    fn parses_room_specs() {
+        let room: RoomSettings = " vip : 5 : 50.5 ".parse().unwrap();
+        assert_eq!(room.name, "vip");
+        assert_eq!(room.min_deposit, 5.0);
+        assert_eq!(room.max_deposit, 50.5);
+    }
+
+    #[test]
+    fn rejects_malformed_room_specs() {
+        for spec in [
+            "",
+            "vip",
+            "vip:5",
+            ":5:50",
+            "vip:five:50",
+            "vip:5:50:1",
+            "vip:5:",
+        ] {
+            assert!(spec.parse::<RoomSettings>().is_err(), "{spec:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rooms() {
+        let rooms = |specs: &[&str]| JackpotSettings {
+            rooms: specs.iter().map(|spec| spec.parse().unwrap()).collect(),
+            ..JackpotSettings::default()
+        };
+        assert!(rooms(&["low:1:10", "high:10:100"]).validate().is_ok());
+        for specs in [
+            &[][..],
+            &["free:0:10"],
+            &["low:10:1"],
+            &["low:1:10", "low:10:100"],
+        ] {
+            assert!(rooms(specs).validate().is_err(), "{specs:?}");
+        }
+    }
+}
@@ -1,23 +1,23 @@
-use actix::{Actor, Addr, SyncContext};
+use std::time::Duration;
+
 use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool};
 
+use crate::repositories::Repositories;
+
 pub type AppDbType = Pool<ConnectionManager<PgConnection>>;
 
 pub struct AppState {
-    pub db: Addr<DbActor>,
-}
-
-pub struct DbActor(pub AppDbType);
-
-impl Actor for DbActor {
-    type Context = SyncContext<Self>;
+    pub repos: Repositories,
 }
 
-pub fn get_db_pool(url: &str) -> AppDbType {
+//* Checking out a connection gives up after `connection_timeout` instead of waiting for one forever */
+pub fn get_db_pool(url: &str, size: u32, connection_timeout: Duration) -> AppDbType {
     let manager = ConnectionManager::new(url);
-    let pool = Pool::builder()
+
+    Pool::builder()
+        .max_size(size)
+        .connection_timeout(connection_timeout)
         .build(manager)
-        .expect("Failed to create database pool");
-    pool
+        .expect("Failed to create database pool")
 }
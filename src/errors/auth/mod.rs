@@ -1,5 +1,8 @@
 use diesel::result::Error as DieselError;
 use thiserror::Error;
+
+use crate::errors::storage::StorageError;
+
 #[derive(Error, Debug)]
 pub enum RegisterError {
     #[error("Forbidden format")]
@@ -10,6 +13,8 @@ pub enum RegisterError {
     InternalError,
     #[error("Diesel error")]
     DieselError(#[from] DieselError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
 }
 
 #[derive(Error, Debug)]
@@ -20,4 +25,16 @@ pub enum LoginError {
     InternalError,
     #[error("Database error")]
     DatabaseError(#[from] DieselError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
+}
+
+#[derive(Error, Debug)]
+pub enum GetUserError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Database error")]
+    DatabaseError(#[from] DieselError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
 }
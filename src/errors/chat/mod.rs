@@ -0,0 +1,37 @@
+use diesel::result::Error as DieselError;
+use thiserror::Error;
+
+use crate::errors::storage::StorageError;
+
+#[derive(Error, Debug)]
+pub enum ChatHistoryError {
+    #[error("Message not found")]
+    MessageNotFound,
+    #[error("Database error")]
+    DatabaseError(#[from] DieselError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
+}
+
+#[derive(Error, Debug)]
+pub enum MessageValidationError {
+    #[error("Message is empty")]
+    Empty,
+    #[error("Message is longer than {0} characters")]
+    TooLong(usize),
+    #[error("Message contains forbidden characters")]
+    ForbiddenCharacters,
+    #[error("New accounts can not post links")]
+    LinksNotAllowed,
+}
+
+impl MessageValidationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MessageValidationError::Empty => "empty",
+            MessageValidationError::TooLong(_) => "too_long",
+            MessageValidationError::ForbiddenCharacters => "forbidden_characters",
+            MessageValidationError::LinksNotAllowed => "links_not_allowed",
+        }
+    }
+}
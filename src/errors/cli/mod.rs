@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+use crate::errors::{
+    auth::{GetUserError, RegisterError},
+    round::RoundJournalError,
+    wallet::WalletError,
+};
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("Could not register user: {0}")]
+    Register(#[from] RegisterError),
+    #[error("Could not find user: {0}")]
+    GetUser(#[from] GetUserError),
+    #[error("Wallet error: {0}")]
+    Wallet(#[from] WalletError),
+    #[error("Round journal error: {0}")]
+    Round(#[from] RoundJournalError),
+    #[error("Round {0} has {1} issue(s)")]
+    RoundMismatch(String, usize),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read config file {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("Could not parse config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid value for {0}")]
+    InvalidEnv(String),
+    #[error("Invalid config: {0}")]
+    Invalid(&'static str),
+}
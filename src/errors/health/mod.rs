@@ -0,0 +1,12 @@
+use diesel::result::Error as DieselError;
+use thiserror::Error;
+
+use crate::errors::storage::StorageError;
+
+#[derive(Error, Debug)]
+pub enum HealthError {
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
+    #[error("Database error")]
+    DatabaseError(#[from] DieselError),
+}
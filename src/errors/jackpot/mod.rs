@@ -0,0 +1,12 @@
+use diesel::result::Error as DieselError;
+use thiserror::Error;
+
+use crate::errors::storage::StorageError;
+
+#[derive(Error, Debug)]
+pub enum RoundHistoryError {
+    #[error("Database error")]
+    DatabaseError(#[from] DieselError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
+}
@@ -0,0 +1,12 @@
+use diesel::r2d2::PoolError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("No database connection available: {0}")]
+    PoolUnavailable(#[from] PoolError),
+    #[error("Migration failed: {0}")]
+    Failed(Box<dyn std::error::Error + Send + Sync>),
+    #[error("Database schema is behind, pending migrations: {}", .0.join(", "))]
+    Pending(Vec<String>),
+}
@@ -1 +1,10 @@
 pub mod auth;
+pub mod chat;
+pub mod cli;
+pub mod config;
+pub mod health;
+pub mod jackpot;
+pub mod migration;
+pub mod round;
+pub mod storage;
+pub mod wallet;
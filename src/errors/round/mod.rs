@@ -0,0 +1,16 @@
+use diesel::result::Error as DieselError;
+use thiserror::Error;
+
+use crate::errors::{storage::StorageError, wallet::WalletError};
+
+#[derive(Error, Debug)]
+pub enum RoundJournalError {
+    #[error("Round not found")]
+    RoundNotFound,
+    #[error("Database error")]
+    DatabaseError(#[from] DieselError),
+    #[error("Refund failed: {0}")]
+    RefundFailed(#[from] WalletError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
+}
@@ -0,0 +1,11 @@
+use diesel::r2d2::PoolError;
+use thiserror::Error;
+
+//* Failures of the storage backend itself, as opposed to a query that ran and failed */
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Timed out waiting for a database connection")]
+    PoolTimeout(#[from] PoolError),
+    #[error("Database task was cancelled")]
+    Cancelled,
+}
@@ -0,0 +1,16 @@
+use diesel::result::Error as DieselError;
+use thiserror::Error;
+
+use crate::errors::storage::StorageError;
+
+#[derive(Error, Debug)]
+pub enum WalletError {
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Database error")]
+    DatabaseError(#[from] DieselError),
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] StorageError),
+}
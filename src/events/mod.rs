@@ -0,0 +1,67 @@
+use actix::{Actor, Context, Handler, Message, Recipient};
+use serde::Serialize;
+
+pub const GAME_JACKPOT: &str = "jackpot";
+pub const GAME_CRASH: &str = "crash";
+pub const GAME_COINFLIP: &str = "coinflip";
+
+//* In-process pub/sub: game servers publish their results, anyone interested subscribes */
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Recipient<GameResult>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus::default()
+    }
+}
+
+impl Actor for EventBus {
+    type Context = Context<Self>;
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Subscribe(pub Recipient<GameResult>);
+
+impl Handler<Subscribe> for EventBus {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _ctx: &mut Self::Context) -> Self::Result {
+        self.subscribers.push(msg.0);
+    }
+}
+
+//* A player won something, published once per winner */
+#[derive(Message, Clone, Debug, Serialize)]
+#[rtype(result = "()")]
+pub struct GameResult {
+    pub game: String,
+    pub round_id: String,
+    pub user_id: i32,
+    pub username: String,
+    pub stake: f64,
+    pub payout: f64,
+}
+
+impl GameResult {
+    pub fn multiplier(&self) -> f64 {
+        if self.stake > 0.0 {
+            self.payout / self.stake
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Handler<GameResult> for EventBus {
+    type Result = ();
+
+    fn handle(&mut self, msg: GameResult, _ctx: &mut Self::Context) -> Self::Result {
+        self.subscribers.retain(|subscriber| subscriber.connected());
+        for subscriber in &self.subscribers {
+            subscriber.do_send(msg.clone());
+        }
+    }
+}
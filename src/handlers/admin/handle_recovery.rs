@@ -0,0 +1,67 @@
+use actix_web::{
+    web::{Data, Query, ReqData},
+    HttpResponse, Responder,
+};
+use jsonwebtoken::TokenData;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    db_utils::AppState, errors::auth::GetUserError, jwt::Claims, models::user::ROLE_ADMIN,
+};
+
+#[derive(Deserialize)]
+pub struct RecoveryQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct AdminRouteError {
+    message: String,
+    status: i32,
+    variant: String,
+}
+
+//* Rounds that were found unfinished on startup and how they were resolved */
+pub async fn handle_recovery_report(
+    query: Query<RecoveryQuery>,
+    claims: ReqData<TokenData<Claims>>,
+    app_state: Data<AppState>,
+) -> impl Responder {
+    let user = app_state.repos.users.find(claims.claims.sub).await;
+    match user {
+        Ok(user) if user.role == ROLE_ADMIN => (),
+        Ok(_) | Err(GetUserError::UserNotFound) => {
+            return HttpResponse::Forbidden().json(AdminRouteError {
+                message: "Admins only".to_string(),
+                status: 403,
+                variant: "Forbidden".to_string(),
+            })
+        }
+        Err(_) => {
+            return HttpResponse::InternalServerError().json(AdminRouteError {
+                message: "Something went wrong".to_string(),
+                status: 500,
+                variant: "InternalServerError".to_string(),
+            })
+        }
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let result = app_state.repos.rounds.recovered(limit, offset).await;
+
+    match result {
+        Ok(rounds) => HttpResponse::Ok().json(json!({
+            "rounds": rounds,
+            "limit": limit,
+            "offset": offset,
+        })),
+        _ => HttpResponse::InternalServerError().json(AdminRouteError {
+            message: "Something went wrong".to_string(),
+            status: 500,
+            variant: "InternalServerError".to_string(),
+        }),
+    }
+}
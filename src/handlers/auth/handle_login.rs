@@ -2,12 +2,13 @@ use actix_web::{
     web::{Data, Json},
     HttpResponse, Responder,
 };
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::{
-    db_utils::AppState, errors::auth::LoginError, jwt::generate_jwt, messages::auth::LoginMessage,
+    accounts, config::Settings, db_utils::AppState, errors::auth::LoginError, jwt::generate_jwt,
+    metrics::metrics,
 };
 
 #[derive(Deserialize)]
@@ -26,20 +27,26 @@ pub struct LoginRouteError {
 pub async fn handle_login(
     payload: Json<LoginPayload>,
     app_state: Data<AppState>,
+    settings: Data<Settings>,
 ) -> impl Responder {
-    let conn = app_state.as_ref().db.clone();
+    let result = accounts::login(
+        app_state.repos.users.as_ref(),
+        payload.username.clone(),
+        payload.password.clone(),
+    )
+    .await;
 
-    let result = conn
-        .send(LoginMessage {
-            username: payload.username.clone(),
-            password: payload.password.clone(),
-        })
-        .await;
+    let outcome = match &result {
+        Ok(_) => "success",
+        Err(LoginError::InvalidCredentials) => "invalid_credentials",
+        Err(_) => "error",
+    };
+    metrics().logins.with_label_values(&[outcome]).inc();
 
     match result {
-        Ok(Ok(user)) => {
+        Ok(user) => {
             let expiration_date = Utc::now()
-                .checked_add_signed(Duration::days(2))
+                .checked_add_signed(settings.auth.jwt_ttl())
                 .expect("invalid timestamp")
                 .timestamp();
             let token =
@@ -62,22 +69,24 @@ pub async fn handle_login(
                 }),
             }
         }
-        Ok(Err(e)) => match e {
+        Err(e) => match e {
             LoginError::InvalidCredentials => HttpResponse::Unauthorized().json(LoginRouteError {
                 message: "Invalid credentials".to_string(),
                 status: 401,
                 variant: "ValidationError".to_string(),
             }),
+            LoginError::Unavailable(_) => {
+                HttpResponse::ServiceUnavailable().json(LoginRouteError {
+                    message: "Service temporarily unavailable".to_string(),
+                    status: 503,
+                    variant: "ServiceUnavailable".to_string(),
+                })
+            }
             _ => HttpResponse::InternalServerError().json(LoginRouteError {
                 message: "Internal server error".to_string(),
                 status: 500,
                 variant: "InternalError".to_string(),
             }),
         },
-        Err(_) => HttpResponse::InternalServerError().json(LoginRouteError {
-            message: "Internal server error".to_string(),
-            status: 500,
-            variant: "InternalError".to_string(),
-        }),
     }
 }
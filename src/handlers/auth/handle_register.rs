@@ -5,7 +5,10 @@ use actix_web::{
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::{db_utils::AppState, errors::auth::RegisterError, messages::auth::RegisterMessage};
+use crate::{
+    accounts, config::Settings, db_utils::AppState, errors::auth::RegisterError,
+    models::user::ROLE_USER,
+};
 #[derive(Serialize)]
 struct RegisterRouteError {
     message: String,
@@ -21,18 +24,26 @@ pub struct RegisterPayload {
 pub async fn handle_register(
     payload: Json<RegisterPayload>,
     app_state: Data<AppState>,
+    settings: Data<Settings>,
 ) -> impl Responder {
-    let conn = app_state.as_ref().db.clone();
-
-    let result = conn
-        .send(RegisterMessage {
-            username: payload.username.clone(),
-            password: payload.password.clone(),
-        })
-        .await;
+    //* The house account is created on demand, so its name is held back from the start */
+    let result = if payload
+        .username
+        .eq_ignore_ascii_case(&settings.house.account_username)
+    {
+        Err(RegisterError::UsernameAlreadyRegistered)
+    } else {
+        accounts::register(
+            app_state.repos.users.as_ref(),
+            payload.username.clone(),
+            payload.password.clone(),
+            ROLE_USER,
+        )
+        .await
+    };
 
     match result {
-        Ok(Ok(user)) => HttpResponse::Created().json(json!({
+        Ok(user) => HttpResponse::Created().json(json!({
             "success":true,
             "status":201,
             "message":"User succesfully registered",
@@ -40,7 +51,7 @@ pub async fn handle_register(
                 "username":user.username,
             }
         })),
-        Ok(Err(err)) => match err {
+        Err(err) => match err {
             RegisterError::ForbiddenFormat => HttpResponse::BadRequest().json(RegisterRouteError {
                 message: "Invalid format".to_string(),
                 status: 400,
@@ -53,6 +64,13 @@ pub async fn handle_register(
                     variant: "FieldTaken".to_string(),
                 })
             }
+            RegisterError::Unavailable(_) => {
+                HttpResponse::ServiceUnavailable().json(RegisterRouteError {
+                    message: "Service temporarily unavailable".to_string(),
+                    status: 503,
+                    variant: "ServiceUnavailable".to_string(),
+                })
+            }
             RegisterError::DieselError(e) => {
                 HttpResponse::InternalServerError().json(RegisterRouteError {
                     message: format!("Database error: {}", e),
@@ -66,10 +84,5 @@ pub async fn handle_register(
                 variant: "InternalServerError".to_string(),
             }),
         },
-        Err(_) => HttpResponse::InternalServerError().json(RegisterRouteError {
-            message: "Something went wrong".to_string(),
-            status: 500,
-            variant: "InternalServerError".to_string(),
-        }),
     }
 }
@@ -0,0 +1,59 @@
+use actix_web::{
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{db_utils::AppState, handlers::websocket::chat::chat_server::GLOBAL_ROOM};
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    room: Option<String>,
+    before_id: Option<i32>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct HistoryRouteError {
+    message: String,
+    status: i32,
+    variant: String,
+}
+
+pub async fn handle_chat_history(
+    query: Query<HistoryQuery>,
+    app_state: Data<AppState>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+
+    let room = query
+        .room
+        .clone()
+        .unwrap_or_else(|| GLOBAL_ROOM.to_string());
+    let result = app_state
+        .repos
+        .chat
+        .history(room, query.before_id, limit)
+        .await;
+
+    match result {
+        Ok(messages) => {
+            //* Cursor for the next (older) page */
+            let next_before_id = if messages.len() as i64 == limit {
+                messages.last().map(|message| message.id)
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(json!({
+                "messages": messages,
+                "next_before_id": next_before_id,
+            }))
+        }
+        _ => HttpResponse::InternalServerError().json(HistoryRouteError {
+            message: "Something went wrong".to_string(),
+            status: 500,
+            variant: "InternalServerError".to_string(),
+        }),
+    }
+}
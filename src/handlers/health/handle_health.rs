@@ -0,0 +1,26 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web::Data, HttpResponse, Responder};
+use serde_json::json;
+
+use crate::health::{CheckStatus, ReadinessProbe};
+
+//* Liveness only, it never touches the database or the actors */
+pub async fn handle_healthz() -> impl Responder {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+pub async fn handle_readyz(probe: Data<ReadinessProbe>) -> impl Responder {
+    let checks = probe.check().await;
+    let ready = checks.iter().all(|(_, status)| *status == CheckStatus::Ok);
+    let body = json!({
+        "status": if ready { "ready" } else { "unavailable" },
+        "checks": checks.into_iter().collect::<BTreeMap<_, _>>(),
+    });
+
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
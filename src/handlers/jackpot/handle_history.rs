@@ -0,0 +1,44 @@
+use actix_web::{
+    web::{Data, Query},
+    HttpResponse, Responder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::db_utils::AppState;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct HistoryRouteError {
+    message: String,
+    status: i32,
+    variant: String,
+}
+
+pub async fn handle_jackpot_history(
+    query: Query<HistoryQuery>,
+    app_state: Data<AppState>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let result = app_state.repos.rounds.jackpot_history(limit, offset).await;
+
+    match result {
+        Ok(rounds) => HttpResponse::Ok().json(json!({
+            "rounds": rounds,
+            "limit": limit,
+            "offset": offset,
+        })),
+        _ => HttpResponse::InternalServerError().json(HistoryRouteError {
+            message: "Something went wrong".to_string(),
+            status: 500,
+            variant: "InternalServerError".to_string(),
+        }),
+    }
+}
@@ -0,0 +1,24 @@
+use actix::Addr;
+use actix_web::{web::Data, HttpResponse, Responder};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::handlers::websocket::jackpot::jackpot_server::{JackpotServer, ListRooms};
+
+#[derive(Serialize)]
+struct RoomsRouteError {
+    message: String,
+    status: i32,
+    variant: String,
+}
+
+pub async fn handle_jackpot_rooms(jackpot_server: Data<Addr<JackpotServer>>) -> impl Responder {
+    match jackpot_server.send(ListRooms).await {
+        Ok(rooms) => HttpResponse::Ok().json(json!({ "rooms": rooms })),
+        Err(_) => HttpResponse::InternalServerError().json(RoomsRouteError {
+            message: "Something went wrong".to_string(),
+            status: 500,
+            variant: "InternalServerError".to_string(),
+        }),
+    }
+}
@@ -0,0 +1,2 @@
+pub mod handle_history;
+pub mod handle_rooms;
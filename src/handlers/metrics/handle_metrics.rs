@@ -0,0 +1,40 @@
+use actix_web::{http::header, web::Data, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::{config::Settings, metrics::metrics};
+
+#[derive(Serialize)]
+struct MetricsRouteError {
+    message: String,
+    status: i32,
+    variant: String,
+}
+
+//* Prometheus scrape target, guarded by a bearer token when metrics.token is set */
+pub async fn handle_metrics(req: HttpRequest, settings: Data<Settings>) -> impl Responder {
+    if let Some(token) = &settings.metrics.token {
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer != Some(token.as_str()) {
+            return HttpResponse::Unauthorized().json(MetricsRouteError {
+                message: "Unauthorized".to_string(),
+                status: 401,
+                variant: "Unauthorized".to_string(),
+            });
+        }
+    }
+
+    match metrics().encode() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .body(body),
+        Err(_) => HttpResponse::InternalServerError().json(MetricsRouteError {
+            message: "Failed to encode metrics".to_string(),
+            status: 500,
+            variant: "InternalError".to_string(),
+        }),
+    }
+}
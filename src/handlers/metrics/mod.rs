@@ -0,0 +1 @@
+pub mod handle_metrics;
@@ -1,2 +1,7 @@
+pub mod admin;
 pub mod auth;
+pub mod chat;
+pub mod health;
+pub mod jackpot;
+pub mod metrics;
 pub mod websocket;
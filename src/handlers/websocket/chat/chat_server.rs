@@ -1,43 +1,866 @@
 use actix::prelude::*;
-use serde::Serialize;
-use std::collections::HashMap;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    config::ChatSettings,
+    errors::{
+        auth::GetUserError,
+        chat::{ChatHistoryError, MessageValidationError},
+        wallet::WalletError,
+    },
+    events::{EventBus, GameResult, Subscribe},
+    messages::health::Ping,
+    models::chat::{
+        ChatMessage, ModerationAction, NewChatMessage, NewModerationAction, ACTION_BAN,
+        ACTION_DELETE, ACTION_MUTE, ACTION_SLOW_MODE, ACTION_UNBAN, ACTION_UNMUTE,
+    },
+    models::wallet::{REASON_CHAT_RAIN, REASON_CHAT_TIP},
+    repositories::{detach, ledger::Transfer, Repositories},
+    shutdown::{Shutdown, SHUTDOWN_NOTICE},
+};
+
+pub const GLOBAL_ROOM: &str = "global";
+//* Lobby rooms of the games, every coinflip game also gets its own "coinflip:<game_id>" room */
+const GAME_ROOMS: [&str; 3] = ["jackpot", "crash", "coinflip"];
+const COINFLIP_ROOM_PREFIX: &str = "coinflip:";
+const LANGUAGE_ROOM_PREFIX: &str = "lang:";
+
 pub struct ChatServer {
-    sessions: HashMap<i32, Recipient<ClientMessage>>,
+    //* Keyed by connection id, a user can have several sockets open */
+    sessions: HashMap<u64, ChatSession>,
+    //* User id -> their connection ids, for moderation and tips which address a user */
+    connections: HashMap<i32, HashSet<u64>>,
+    //* Room name -> connection ids of the members */
+    rooms: HashMap<String, HashSet<u64>>,
+    //* Most recent messages per room, oldest first, replayed to everyone joining the room */
+    history: HashMap<String, VecDeque<ClientMessage>>,
+    //* User id -> end of the mute */
+    muted: HashMap<i32, NaiveDateTime>,
+    //* User id -> end of the ban, None for permanent bans */
+    banned: HashMap<i32, Option<NaiveDateTime>>,
+    //* Room -> minimum time between two messages of the same user */
+    slow_mode: HashMap<String, Duration>,
+    //* When each user last posted in each room, used by slow mode and rain */
+    last_posts: HashMap<(i32, String), Instant>,
+    settings: Arc<ChatSettings>,
+    repos: Repositories,
+    events: Addr<EventBus>,
+}
+
+struct ChatSession {
+    user_id: i32,
+    username: Option<String>,
+    is_moderator: bool,
+    addr: Recipient<ClientMessage>,
+    events: Recipient<ChatEvent>,
+}
+
+//* Checked by every socket before a line is posted, the banned words are compiled once for all of them */
+#[derive(Clone)]
+pub struct MessageFilter {
+    settings: Arc<ChatSettings>,
+    word_filter: Option<Regex>,
+}
+
+impl MessageFilter {
+    //* Settings::load already rejected banned words that don't compile */
+    pub fn new(settings: Arc<ChatSettings>) -> Self {
+        MessageFilter {
+            word_filter: settings.word_filter().ok().flatten(),
+            settings,
+        }
+    }
+
+    //* Returns the text to post, trimmed and with banned words masked */
+    pub fn check_message(
+        &self,
+        text: &str,
+        account_created_at: Option<NaiveDateTime>,
+    ) -> Result<String, MessageValidationError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(MessageValidationError::Empty);
+        }
+        let max_length = self.settings.max_length;
+        if text.chars().count() > max_length {
+            return Err(MessageValidationError::TooLong(max_length));
+        }
+        //* Only control characters are refused, apostrophes and quotes are ordinary chat */
+        if text.chars().any(char::is_control) {
+            return Err(MessageValidationError::ForbiddenCharacters);
+        }
+        let is_new_account = account_created_at
+            .map(|created_at| {
+                created_at + self.settings.link_min_account_age() > Utc::now().naive_utc()
+            })
+            .unwrap_or(true);
+        if is_new_account && contains_link(text) {
+            return Err(MessageValidationError::LinksNotAllowed);
+        }
+
+        Ok(match &self.word_filter {
+            Some(filter) => filter
+                .replace_all(text, |caps: &regex::Captures| {
+                    "*".repeat(caps[0].chars().count())
+                })
+                .into_owned(),
+            None => text.to_string(),
+        })
+    }
+}
+
+static LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(https?://|www\.)\S+|\b[a-z0-9-]+\.(com|net|org|io|gg|xyz|ru|me|co|ly|tk)\b")
+        .unwrap()
+});
+
+fn contains_link(text: &str) -> bool {
+    LINK.is_match(text)
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RoomMember {
+    pub id: i32,
+    pub username: Option<String>,
 }
 
 impl ChatServer {
-    pub fn new() -> Self {
+    pub fn new(repos: Repositories, settings: Arc<ChatSettings>, events: Addr<EventBus>) -> Self {
         ChatServer {
             sessions: HashMap::new(),
+            connections: HashMap::new(),
+            rooms: HashMap::new(),
+            history: HashMap::new(),
+            muted: HashMap::new(),
+            banned: HashMap::new(),
+            slow_mode: HashMap::new(),
+            last_posts: HashMap::new(),
+            settings,
+            repos,
+            events,
+        }
+    }
+
+    fn is_valid_room(&self, room: &str) -> bool {
+        if room == GLOBAL_ROOM || GAME_ROOMS.contains(&room) {
+            return true;
+        }
+        if let Some(game_id) = room.strip_prefix(COINFLIP_ROOM_PREFIX) {
+            return !game_id.is_empty()
+                && game_id.len() <= 64
+                && game_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        }
+        if let Some(lang) = room.strip_prefix(LANGUAGE_ROOM_PREFIX) {
+            return self.settings.languages.iter().any(|l| l == lang);
+        }
+        false
+    }
+
+    fn remember(&mut self, msg: ClientMessage) {
+        let history = self.history.entry(msg.room.clone()).or_default();
+        history.push_back(msg);
+        while history.len() > self.settings.history_size {
+            history.pop_front();
+        }
+    }
+
+    //* Older messages loaded from the database go in front of whatever arrived meanwhile */
+    fn remember_loaded(&mut self, room: &str, messages: Vec<ChatMessage>) {
+        let history = self.history.entry(room.to_string()).or_default();
+        let known: HashSet<String> = history.iter().map(|m| m.message_id.clone()).collect();
+        //* History comes back newest first */
+        for message in messages {
+            if !known.contains(&message.message_id) {
+                history.push_front(ClientMessage::from(message));
+            }
+        }
+        while history.len() > self.settings.history_size {
+            history.pop_front();
+        }
+    }
+
+    fn is_member(&self, conn_id: u64, room: &str) -> bool {
+        self.rooms
+            .get(room)
+            .map(|members| members.contains(&conn_id))
+            .unwrap_or(false)
+    }
+
+    //* Whether another socket of the user is in the room, joins and leaves are only announced once per user */
+    fn user_in_room(&self, user_id: i32, room: &str, except: u64) -> bool {
+        self.rooms
+            .get(room)
+            .map(|members| {
+                members.iter().any(|conn_id| {
+                    *conn_id != except
+                        && self
+                            .sessions
+                            .get(conn_id)
+                            .is_some_and(|session| session.user_id == user_id)
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    fn member(&self, conn_id: u64) -> Option<RoomMember> {
+        self.sessions.get(&conn_id).map(|session| RoomMember {
+            id: session.user_id,
+            username: session.username.clone(),
+        })
+    }
+
+    fn members(&self, room: &str) -> Vec<RoomMember> {
+        let mut listed = HashSet::new();
+        self.rooms
+            .get(room)
+            .map(|members| {
+                members
+                    .iter()
+                    .filter_map(|conn_id| self.member(*conn_id))
+                    .filter(|member| listed.insert(member.id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn username(&self, user_id: i32) -> Option<String> {
+        self.connections
+            .get(&user_id)?
+            .iter()
+            .find_map(|conn_id| self.sessions.get(conn_id)?.username.clone())
+    }
+
+    //* Anonymous sockets never moderate, whatever their session says */
+    fn can_moderate(&self, user_id: i32) -> bool {
+        self.connections
+            .get(&user_id)
+            .map(|conn_ids| {
+                conn_ids.iter().any(|conn_id| {
+                    self.sessions
+                        .get(conn_id)
+                        .is_some_and(|session| session.is_moderator && session.username.is_some())
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    fn check_sanctions(&mut self, id: i32) -> Result<(), PostError> {
+        let now = Utc::now().naive_utc();
+        if let Some(until) = self.banned.get(&id) {
+            match until {
+                Some(until) if *until <= now => {
+                    self.banned.remove(&id);
+                }
+                _ => return Err(PostError::Banned),
+            }
+        }
+        if let Some(until) = self.muted.get(&id) {
+            if *until > now {
+                return Err(PostError::Muted(until.and_utc()));
+            }
+            self.muted.remove(&id);
+        }
+        Ok(())
+    }
+
+    fn check_post(&mut self, conn_id: u64, msg: &ClientMessage) -> Result<(), PostError> {
+        if !self.is_member(conn_id, &msg.room) {
+            return Err(PostError::NotAMember);
+        }
+        self.check_sanctions(msg.id)?;
+        if self.can_moderate(msg.id) {
+            return Ok(());
+        }
+        if let (Some(interval), Some(last)) = (
+            self.slow_mode.get(&msg.room),
+            self.last_posts.get(&(msg.id, msg.room.clone())),
+        ) {
+            let elapsed = last.elapsed();
+            if elapsed < *interval {
+                return Err(PostError::SlowMode((*interval - elapsed).as_secs() + 1));
+            }
+        }
+        Ok(())
+    }
+
+    //* Users who posted in the room recently and are still connected, most recent first */
+    fn recent_chatters(&self, room: &str, except: i32) -> Vec<(i32, String)> {
+        let mut chatters: Vec<(Instant, i32, String)> = self
+            .last_posts
+            .iter()
+            .filter(|((id, posted_in), last)| {
+                *id != except && posted_in == room && last.elapsed() <= self.settings.rain_window()
+            })
+            .filter_map(|((id, _), last)| {
+                let username = self.username(*id)?;
+                Some((*last, *id, username))
+            })
+            .collect();
+        chatters.sort_by_key(|(last, _, _)| std::cmp::Reverse(*last));
+        chatters
+            .into_iter()
+            .take(self.settings.rain_max_recipients)
+            .map(|(_, id, username)| (id, username))
+            .collect()
+    }
+
+    //* System messages are not persisted, they only live in the in-memory history */
+    fn post_system(&mut self, room: &str, text: String) {
+        let msg = ClientMessage::system(room, text);
+        self.broadcast(room, &msg);
+        self.remember(msg);
+    }
+
+    //* Applies a persisted action, `live` is false while replaying them on startup */
+    fn apply(&mut self, action: &ModerationAction, live: bool) {
+        let target = action.target_user_id;
+        let payload = json!({
+            "user_id": target,
+            "until": action.expires_at.map(|until| until.and_utc()),
+            "reason": action.reason,
+        });
+        match (action.action.as_str(), target) {
+            (ACTION_MUTE, Some(user_id)) => {
+                if let Some(until) = action.expires_at {
+                    self.muted.insert(user_id, until);
+                }
+            }
+            (ACTION_UNMUTE, Some(user_id)) => {
+                self.muted.remove(&user_id);
+            }
+            (ACTION_BAN, Some(user_id)) => {
+                self.banned.insert(user_id, action.expires_at);
+            }
+            (ACTION_UNBAN, Some(user_id)) => {
+                self.banned.remove(&user_id);
+            }
+            (ACTION_SLOW_MODE, _) => {
+                let Some(room) = &action.room else {
+                    return;
+                };
+                let interval = action.duration_secs.unwrap_or(0).max(0) as u64;
+                if interval == 0 {
+                    self.slow_mode.remove(room);
+                } else {
+                    self.slow_mode
+                        .insert(room.clone(), Duration::from_secs(interval));
+                }
+                if live {
+                    self.broadcast_event(
+                        room,
+                        ChatEvent::new("slow_mode", room, json!({ "interval_secs": interval })),
+                    );
+                }
+                return;
+            }
+            (ACTION_DELETE, _) => {
+                let (Some(room), Some(message_id)) = (&action.room, &action.message_id) else {
+                    return;
+                };
+                if let Some(history) = self.history.get_mut(room) {
+                    history.retain(|message| &message.message_id != message_id);
+                }
+                if live {
+                    self.broadcast_event(
+                        room,
+                        ChatEvent::new(
+                            "message_deleted",
+                            room,
+                            json!({ "message_id": message_id }),
+                        ),
+                    );
+                }
+                return;
+            }
+            _ => return,
+        }
+        //* Mutes and bans are only told to the user they target */
+        if let (true, Some(user_id)) = (live, target) {
+            self.send_to_user(user_id, ChatEvent::direct(&action.action, payload));
+        }
+    }
+
+    fn broadcast(&self, room: &str, msg: &ClientMessage) {
+        if let Some(members) = self.rooms.get(room) {
+            for id in members {
+                if let Some(session) = self.sessions.get(id) {
+                    session.addr.do_send(msg.clone());
+                }
+            }
+        }
+    }
+
+    fn broadcast_event(&self, room: &str, event: ChatEvent) {
+        if let Some(members) = self.rooms.get(room) {
+            for id in members {
+                if let Some(session) = self.sessions.get(id) {
+                    session.events.do_send(event.clone());
+                }
+            }
+        }
+    }
+
+    fn send_event(&self, conn_id: u64, event: ChatEvent) {
+        if let Some(session) = self.sessions.get(&conn_id) {
+            session.events.do_send(event);
+        }
+    }
+
+    //* Reaches every socket of the user */
+    fn send_to_user(&self, user_id: i32, event: ChatEvent) {
+        for conn_id in self.connections.get(&user_id).into_iter().flatten() {
+            self.send_event(*conn_id, event.clone());
+        }
+    }
+
+    fn send_members(&self, conn_id: u64, room: &str) {
+        self.send_event(
+            conn_id,
+            ChatEvent::new("members", room, json!({ "members": self.members(room) })),
+        );
+    }
+
+    fn send_history(&self, conn_id: u64, room: &str) {
+        if let (Some(session), Some(history)) =
+            (self.sessions.get(&conn_id), self.history.get(room))
+        {
+            for message in history {
+                session.addr.do_send(message.clone());
+            }
+        }
+    }
+
+    fn join(&mut self, conn_id: u64, room: String, ctx: &mut Context<Self>) {
+        if self.is_member(conn_id, &room) {
+            return;
+        }
+        let Some(member) = self.member(conn_id) else {
+            return;
+        };
+        if !self.user_in_room(member.id, &room, conn_id) {
+            self.broadcast_event(&room, ChatEvent::new("member_joined", &room, json!(member)));
+        }
+        self.rooms.entry(room.clone()).or_default().insert(conn_id);
+        self.send_members(conn_id, &room);
+
+        if self.history.contains_key(&room) {
+            self.send_history(conn_id, &room);
+            return;
+        }
+        let request =
+            self.repos
+                .chat
+                .history(room.clone(), None, self.settings.history_size as i64);
+        ctx.spawn(request.into_actor(self).map(move |result, act, _ctx| {
+            match result {
+                Ok(messages) => act.remember_loaded(&room, messages),
+                _ => error!(room = %room, "Failed to load chat history"),
+            }
+            if act.is_member(conn_id, &room) {
+                act.send_history(conn_id, &room);
+            }
+        }));
+    }
+
+    fn leave(&mut self, conn_id: u64, room: &str) {
+        let Some(members) = self.rooms.get_mut(room) else {
+            return;
+        };
+        members.remove(&conn_id);
+        if members.is_empty() {
+            self.rooms.remove(room);
+            //* Per game rooms die with the game, nobody will ask for their history again */
+            if room.starts_with(COINFLIP_ROOM_PREFIX) {
+                self.history.remove(room);
+            }
+            return;
+        }
+        let Some(member) = self.member(conn_id) else {
+            return;
+        };
+        if !self.user_in_room(member.id, room, conn_id) {
+            self.broadcast_event(room, ChatEvent::new("member_left", room, json!(member)));
         }
     }
 }
 
 impl Actor for ChatServer {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.events.do_send(Subscribe(ctx.address().recipient()));
+
+        let request = self.repos.chat.history(
+            GLOBAL_ROOM.to_string(),
+            None,
+            self.settings.history_size as i64,
+        );
+        ctx.spawn(
+            request
+                .into_actor(self)
+                .map(|result, act, _ctx| match result {
+                    Ok(messages) => act.remember_loaded(GLOBAL_ROOM, messages),
+                    _ => error!(room = GLOBAL_ROOM, "Failed to load chat history"),
+                }),
+        );
+
+        ctx.spawn(
+            self.repos
+                .chat
+                .active_moderation()
+                .into_actor(self)
+                .map(|result, act, _ctx| match result {
+                    Ok(actions) => {
+                        for action in &actions {
+                            act.apply(action, false);
+                        }
+                    }
+                    _ => error!("Failed to load chat moderation actions"),
+                }),
+        );
+    }
 }
+
+//* Everyone starts out in the global room */
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct Connect {
+    pub conn_id: u64,
     pub id: i32,
+    pub username: Option<String>,
+    pub is_moderator: bool,
     pub addr: Recipient<ClientMessage>,
+    pub events: Recipient<ChatEvent>,
 }
 
 impl Handler<Connect> for ChatServer {
     type Result = ();
-    fn handle(&mut self, msg: Connect, _ctx: &mut Self::Context) -> Self::Result {
-        self.sessions.insert(msg.id, msg.addr);
+    fn handle(&mut self, msg: Connect, ctx: &mut Self::Context) -> Self::Result {
+        self.sessions.insert(
+            msg.conn_id,
+            ChatSession {
+                user_id: msg.id,
+                username: msg.username,
+                is_moderator: msg.is_moderator,
+                addr: msg.addr,
+                events: msg.events,
+            },
+        );
+        self.connections
+            .entry(msg.id)
+            .or_default()
+            .insert(msg.conn_id);
+        self.join(msg.conn_id, GLOBAL_ROOM.to_string(), ctx);
     }
 }
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct Disconnect {
-    pub id: i32,
+    pub conn_id: u64,
 }
 impl Handler<Disconnect> for ChatServer {
     type Result = ();
     fn handle(&mut self, msg: Disconnect, _ctx: &mut Self::Context) -> Self::Result {
-        self.sessions.remove(&msg.id);
+        let joined: Vec<String> = self
+            .rooms
+            .iter()
+            .filter(|(_, members)| members.contains(&msg.conn_id))
+            .map(|(room, _)| room.clone())
+            .collect();
+        for room in joined {
+            self.leave(msg.conn_id, &room);
+        }
+        let Some(session) = self.sessions.remove(&msg.conn_id) else {
+            return;
+        };
+        let Some(conn_ids) = self.connections.get_mut(&session.user_id) else {
+            return;
+        };
+        conn_ids.remove(&msg.conn_id);
+        //* Rain only falls on users who are still connected */
+        if conn_ids.is_empty() {
+            self.connections.remove(&session.user_id);
+            self.last_posts.retain(|(id, _), _| *id != session.user_id);
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ChatRoomError {
+    #[error("Room not found")]
+    RoomNotFound,
+    #[error("Not connected")]
+    NotConnected,
+    #[error("Not a member of this room")]
+    NotAMember,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(),ChatRoomError>")]
+pub struct JoinRoom {
+    pub conn_id: u64,
+    pub room: String,
+}
+
+impl Handler<JoinRoom> for ChatServer {
+    type Result = Result<(), ChatRoomError>;
+
+    fn handle(&mut self, msg: JoinRoom, ctx: &mut Self::Context) -> Self::Result {
+        if !self.is_valid_room(&msg.room) {
+            return Err(ChatRoomError::RoomNotFound);
+        }
+        if !self.sessions.contains_key(&msg.conn_id) {
+            return Err(ChatRoomError::NotConnected);
+        }
+        self.join(msg.conn_id, msg.room, ctx);
+        Ok(())
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(),ChatRoomError>")]
+pub struct LeaveRoom {
+    pub conn_id: u64,
+    pub room: String,
+}
+
+impl Handler<LeaveRoom> for ChatServer {
+    type Result = Result<(), ChatRoomError>;
+
+    fn handle(&mut self, msg: LeaveRoom, _ctx: &mut Self::Context) -> Self::Result {
+        if !self.is_member(msg.conn_id, &msg.room) {
+            return Err(ChatRoomError::NotAMember);
+        }
+        self.leave(msg.conn_id, &msg.room);
+        self.send_event(msg.conn_id, ChatEvent::new("left", &msg.room, Value::Null));
+        Ok(())
+    }
+}
+
+//* The member list is pushed to the requester as a "members" event */
+#[derive(Message)]
+#[rtype(result = "Result<(),ChatRoomError>")]
+pub struct ListMembers {
+    pub conn_id: u64,
+    pub room: String,
+}
+
+impl Handler<ListMembers> for ChatServer {
+    type Result = Result<(), ChatRoomError>;
+
+    fn handle(&mut self, msg: ListMembers, _ctx: &mut Self::Context) -> Self::Result {
+        if !self.is_member(msg.conn_id, &msg.room) {
+            return Err(ChatRoomError::NotAMember);
+        }
+        self.send_members(msg.conn_id, &msg.room);
+        Ok(())
+    }
+}
+
+//* Room membership changes and replies, chat lines themselves are sent as ClientMessage */
+#[derive(Message, Clone, Serialize)]
+#[rtype(result = "()")]
+pub struct ChatEvent {
+    pub variant: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    pub payload: Value,
+}
+
+impl ChatEvent {
+    pub fn new(variant: &str, room: &str, payload: Value) -> Self {
+        ChatEvent {
+            variant: variant.to_string(),
+            room: Some(room.to_string()),
+            payload,
+        }
+    }
+
+    //* Events about the user rather than a room */
+    pub fn direct(variant: &str, payload: Value) -> Self {
+        ChatEvent {
+            variant: variant.to_string(),
+            room: None,
+            payload,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PostError {
+    #[error("Not a member of this room")]
+    NotAMember,
+    #[error("You are banned from chat")]
+    Banned,
+    #[error("You are muted until {0}")]
+    Muted(DateTime<Utc>),
+    #[error("Slow mode is on, wait {0} seconds")]
+    SlowMode(u64),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ModerationCommand {
+    Delete {
+        message_id: String,
+        reason: Option<String>,
+    },
+    Mute {
+        user_id: i32,
+        duration_secs: u64,
+        reason: Option<String>,
+    },
+    Unmute {
+        user_id: i32,
+    },
+    Ban {
+        user_id: i32,
+        //* Permanent when missing */
+        duration_secs: Option<u64>,
+        reason: Option<String>,
+    },
+    Unban {
+        user_id: i32,
+    },
+    SlowMode {
+        room: String,
+        //* 0 turns slow mode off */
+        interval_secs: u64,
+    },
+}
+
+impl ModerationCommand {
+    fn into_action(self, moderator_id: i32) -> NewModerationAction {
+        let now = Utc::now().naive_utc();
+        let expires = |secs: u64| now + chrono::Duration::seconds(secs as i64);
+        match self {
+            ModerationCommand::Delete { message_id, reason } => NewModerationAction {
+                action: ACTION_DELETE.to_string(),
+                moderator_id,
+                message_id: Some(message_id),
+                reason,
+                ..Default::default()
+            },
+            ModerationCommand::Mute {
+                user_id,
+                duration_secs,
+                reason,
+            } => NewModerationAction {
+                action: ACTION_MUTE.to_string(),
+                moderator_id,
+                target_user_id: Some(user_id),
+                duration_secs: Some(duration_secs as i64),
+                reason,
+                expires_at: Some(expires(duration_secs)),
+                ..Default::default()
+            },
+            ModerationCommand::Unmute { user_id } => NewModerationAction {
+                action: ACTION_UNMUTE.to_string(),
+                moderator_id,
+                target_user_id: Some(user_id),
+                ..Default::default()
+            },
+            ModerationCommand::Ban {
+                user_id,
+                duration_secs,
+                reason,
+            } => NewModerationAction {
+                action: ACTION_BAN.to_string(),
+                moderator_id,
+                target_user_id: Some(user_id),
+                duration_secs: duration_secs.map(|secs| secs as i64),
+                reason,
+                expires_at: duration_secs.map(expires),
+                ..Default::default()
+            },
+            ModerationCommand::Unban { user_id } => NewModerationAction {
+                action: ACTION_UNBAN.to_string(),
+                moderator_id,
+                target_user_id: Some(user_id),
+                ..Default::default()
+            },
+            ModerationCommand::SlowMode {
+                room,
+                interval_secs,
+            } => NewModerationAction {
+                action: ACTION_SLOW_MODE.to_string(),
+                moderator_id,
+                room: Some(room),
+                duration_secs: Some(interval_secs as i64),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ModerationError {
+    #[error("Not allowed")]
+    NotAllowed,
+    #[error("Room not found")]
+    RoomNotFound,
+    #[error("Message not found")]
+    MessageNotFound,
+    #[error("Internal error")]
+    InternalError,
+}
+
+//* The action is persisted first and only applied once it is stored */
+#[derive(Message)]
+#[rtype(result = "Result<(),ModerationError>")]
+pub struct Moderate {
+    pub moderator_id: i32,
+    pub command: ModerationCommand,
+}
+
+impl Handler<Moderate> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<(), ModerationError>>;
+
+    fn handle(&mut self, msg: Moderate, _ctx: &mut Self::Context) -> Self::Result {
+        if !self.can_moderate(msg.moderator_id) {
+            return Box::pin(fut::ready(Err(ModerationError::NotAllowed)));
+        }
+        if let ModerationCommand::SlowMode { room, .. } = &msg.command {
+            if !self.is_valid_room(room) {
+                return Box::pin(fut::ready(Err(ModerationError::RoomNotFound)));
+            }
+        }
+        let chat = self.repos.chat.clone();
+        let mut action = msg.command.into_action(msg.moderator_id);
+
+        Box::pin(
+            async move {
+                if let Some(message_id) = &action.message_id {
+                    let deleted = chat.delete(message_id.clone()).await.map_err(|e| match e {
+                        ChatHistoryError::MessageNotFound => ModerationError::MessageNotFound,
+                        _ => ModerationError::InternalError,
+                    })?;
+                    action.room = Some(deleted.room);
+                }
+                chat.record_moderation(action)
+                    .await
+                    .map_err(|_| ModerationError::InternalError)
+            }
+            .into_actor(self)
+            .map(|result, act, _ctx| {
+                let action = result?;
+                act.apply(&action, true);
+                Ok(())
+            }),
+        )
     }
 }
 
@@ -45,14 +868,350 @@ impl Handler<Disconnect> for ChatServer {
 #[rtype(result = "()")]
 pub struct ClientMessage {
     pub id: i32,
+    pub username: String,
     pub msg: String,
+    pub message_id: String,
+    pub room: String,
+    pub created_at: DateTime<Utc>,
+    pub system: bool,
+}
+
+pub const SYSTEM_USER_ID: i32 = 0;
+pub const SYSTEM_USERNAME: &str = "system";
+
+impl ClientMessage {
+    pub fn system(room: &str, msg: String) -> Self {
+        ClientMessage {
+            id: SYSTEM_USER_ID,
+            username: SYSTEM_USERNAME.to_string(),
+            msg,
+            message_id: Uuid::new_v4().to_string(),
+            room: room.to_string(),
+            created_at: Utc::now(),
+            system: true,
+        }
+    }
+}
+
+impl From<ChatMessage> for ClientMessage {
+    fn from(message: ChatMessage) -> Self {
+        ClientMessage {
+            id: message.user_id,
+            username: message.username,
+            msg: message.message,
+            message_id: message.message_id,
+            room: message.room,
+            created_at: message.created_at.and_utc(),
+            system: false,
+        }
+    }
+}
+
+//* A chat line from one of the sockets, a rejection only goes back to that socket */
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Post {
+    pub conn_id: u64,
+    pub message: ClientMessage,
+}
+
+impl Handler<Post> for ChatServer {
+    type Result = ();
+    fn handle(&mut self, post: Post, _ctx: &mut Self::Context) -> Self::Result {
+        let msg = post.message;
+        if let Err(e) = self.check_post(post.conn_id, &msg) {
+            self.send_event(
+                post.conn_id,
+                ChatEvent::new(
+                    "message_rejected",
+                    &msg.room,
+                    json!({ "reason": e.to_string() }),
+                ),
+            );
+            return;
+        }
+        self.last_posts
+            .insert((msg.id, msg.room.clone()), Instant::now());
+        detach(
+            "save_chat_message",
+            self.repos.chat.save(NewChatMessage {
+                message_id: msg.message_id.clone(),
+                user_id: msg.id,
+                username: msg.username.clone(),
+                message: msg.msg.clone(),
+                created_at: msg.created_at.naive_utc(),
+                room: msg.room.clone(),
+            }),
+        );
+        self.broadcast(&msg.room, &msg);
+        self.remember(msg);
+    }
+}
+
+impl Handler<GameResult> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: GameResult, _ctx: &mut Self::Context) -> Self::Result {
+        if msg.payout < self.settings.announce_min_payout {
+            return;
+        }
+        self.post_system(
+            GLOBAL_ROOM,
+            format!(
+                "{} won {:.2} on {} ({:.2}x)",
+                msg.username,
+                msg.payout,
+                msg.game,
+                msg.multiplier()
+            ),
+        );
+    }
+}
+
+//* Every socket is in the global room, so the notice reaches all of them */
+impl Handler<Shutdown> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Shutdown, _ctx: &mut Self::Context) -> Self::Result {
+        self.post_system(GLOBAL_ROOM, SHUTDOWN_NOTICE.to_string());
+    }
 }
 
-impl Handler<ClientMessage> for ChatServer {
+impl Handler<Ping> for ChatServer {
     type Result = ();
-    fn handle(&mut self, msg: ClientMessage, _ctx: &mut Self::Context) -> Self::Result {
-        for (_id, addr) in &self.sessions {
-            addr.do_send(msg.clone());
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+#[derive(Error, Debug)]
+pub enum ChatCommandError {
+    #[error("Unknown command")]
+    UnknownCommand,
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Amount must be at least {0}")]
+    AmountTooSmall(f64),
+    #[error("User not found")]
+    UserNotFound,
+    #[error("You can not tip yourself")]
+    CannotTipYourself,
+    #[error("Nobody has been chatting here recently")]
+    NoRecipients,
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    #[error(transparent)]
+    NotAllowed(#[from] PostError),
+    #[error("Something went wrong")]
+    InternalError,
+}
+
+impl From<WalletError> for ChatCommandError {
+    fn from(e: WalletError) -> Self {
+        match e {
+            WalletError::InsufficientFunds => ChatCommandError::InsufficientFunds,
+            WalletError::UserNotFound => ChatCommandError::UserNotFound,
+            _ => ChatCommandError::InternalError,
+        }
+    }
+}
+
+//* /tip <user> <amount> */
+#[derive(Message)]
+#[rtype(result = "Result<(),ChatCommandError>")]
+pub struct Tip {
+    pub from_id: i32,
+    pub room: String,
+    pub to_username: String,
+    pub amount: f64,
+}
+
+impl Handler<Tip> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<(), ChatCommandError>>;
+
+    fn handle(&mut self, msg: Tip, _ctx: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self.check_sanctions(msg.from_id) {
+            return Box::pin(fut::ready(Err(e.into())));
+        }
+        if msg.amount.is_nan() || msg.amount < self.settings.min_transfer {
+            return Box::pin(fut::ready(Err(ChatCommandError::AmountTooSmall(
+                self.settings.min_transfer,
+            ))));
         }
+        let from_name = self.username(msg.from_id).unwrap_or_default();
+        let repos = self.repos.clone();
+        let (from_id, to_username, amount) = (msg.from_id, msg.to_username, msg.amount);
+
+        Box::pin(
+            async move {
+                let recipient =
+                    repos
+                        .users
+                        .find_by_username(to_username)
+                        .await
+                        .map_err(|e| match e {
+                            GetUserError::UserNotFound => ChatCommandError::UserNotFound,
+                            _ => ChatCommandError::InternalError,
+                        })?;
+                if recipient.id == from_id {
+                    return Err(ChatCommandError::CannotTipYourself);
+                }
+                repos
+                    .ledger
+                    .transfer(Transfer {
+                        from_user_id: from_id,
+                        recipients: vec![(recipient.id, amount)],
+                        reason: REASON_CHAT_TIP.to_string(),
+                        reference: Some(Uuid::new_v4().to_string()),
+                    })
+                    .await?;
+                Ok(recipient.username)
+            }
+            .into_actor(self)
+            .map(move |result, act, _ctx| {
+                let to_name = result?;
+                act.post_system(
+                    &msg.room,
+                    format!("{} tipped {} {:.2}", from_name, to_name, amount),
+                );
+                Ok(())
+            }),
+        )
+    }
+}
+
+//* /rain <amount>, split evenly among the recent chatters of the room */
+#[derive(Message)]
+#[rtype(result = "Result<(),ChatCommandError>")]
+pub struct Rain {
+    pub from_id: i32,
+    pub room: String,
+    pub amount: f64,
+}
+
+impl Handler<Rain> for ChatServer {
+    type Result = ResponseActFuture<Self, Result<(), ChatCommandError>>;
+
+    fn handle(&mut self, msg: Rain, _ctx: &mut Self::Context) -> Self::Result {
+        if let Err(e) = self.check_sanctions(msg.from_id) {
+            return Box::pin(fut::ready(Err(e.into())));
+        }
+        let chatters = self.recent_chatters(&msg.room, msg.from_id);
+        if chatters.is_empty() {
+            return Box::pin(fut::ready(Err(ChatCommandError::NoRecipients)));
+        }
+        //* Shares are rounded down to cents, the remainder stays with the sender */
+        let share = (msg.amount / chatters.len() as f64 * 100.0).floor() / 100.0;
+        if share.is_nan() || share < self.settings.min_transfer {
+            return Box::pin(fut::ready(Err(ChatCommandError::AmountTooSmall(
+                self.settings.min_transfer * chatters.len() as f64,
+            ))));
+        }
+        let from_name = self.username(msg.from_id).unwrap_or_default();
+        let transfer = Transfer {
+            from_user_id: msg.from_id,
+            recipients: chatters.iter().map(|(id, _)| (*id, share)).collect(),
+            reason: REASON_CHAT_RAIN.to_string(),
+            reference: Some(Uuid::new_v4().to_string()),
+        };
+
+        Box::pin(self.repos.ledger.transfer(transfer).into_actor(self).map(
+            move |result, act, _ctx| {
+                result?;
+                let names: Vec<String> = chatters.into_iter().map(|(_, name)| name).collect();
+                act.post_system(
+                    &msg.room,
+                    format!(
+                        "{} made it rain {:.2} on {} chatters ({:.2} each): {}",
+                        from_name,
+                        share * names.len() as f64,
+                        names.len(),
+                        share,
+                        names.join(", ")
+                    ),
+                );
+                Ok(())
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn filter(settings: ChatSettings) -> MessageFilter {
+        MessageFilter::new(Arc::new(settings))
+    }
+
+    fn old_account() -> Option<NaiveDateTime> {
+        Some(Utc::now().naive_utc() - Duration::days(30))
+    }
+
+    #[test]
+    fn rejects_empty_and_long_messages() {
+        let filter = filter(ChatSettings {
+            max_length: 5,
+            ..ChatSettings::default()
+        });
+        assert!(matches!(
+            filter.check_message("   ", old_account()),
+            Err(MessageValidationError::Empty)
+        ));
+        assert!(matches!(
+            filter.check_message("abcdef", old_account()),
+            Err(MessageValidationError::TooLong(5))
+        ));
+        assert_eq!(
+            filter.check_message("ñañañ", old_account()).unwrap(),
+            "ñañañ"
+        );
+    }
+
+    #[test]
+    fn rejects_control_characters_only() {
+        let filter = filter(ChatSettings::default());
+        assert!(matches!(
+            filter.check_message("hi\u{7}there", old_account()),
+            Err(MessageValidationError::ForbiddenCharacters)
+        ));
+        assert!(matches!(
+            filter.check_message("line\nbreak", old_account()),
+            Err(MessageValidationError::ForbiddenCharacters)
+        ));
+        let text = r#"it's "gg" <3 & 2 > 1"#;
+        assert_eq!(filter.check_message(text, old_account()).unwrap(), text);
+    }
+
+    #[test]
+    fn new_accounts_can_not_post_links() {
+        let filter = filter(ChatSettings::default());
+        let new_account = Some(Utc::now().naive_utc() - Duration::hours(1));
+        for text in ["see https://x.example", "www.example.org", "join scam.gg"] {
+            assert!(matches!(
+                filter.check_message(text, new_account),
+                Err(MessageValidationError::LinksNotAllowed)
+            ));
+            assert!(matches!(
+                filter.check_message(text, None),
+                Err(MessageValidationError::LinksNotAllowed)
+            ));
+            assert!(filter.check_message(text, old_account()).is_ok());
+        }
+    }
+
+    #[test]
+    fn masks_banned_words() {
+        let filter = filter(ChatSettings {
+            banned_words: vec!["scam".to_string()],
+            ..ChatSettings::default()
+        });
+        assert_eq!(
+            filter
+                .check_message("SCAM alert, not scammer", old_account())
+                .unwrap(),
+            "**** alert, not scammer"
+        );
     }
 }
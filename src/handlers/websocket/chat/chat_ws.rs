@@ -1,43 +1,333 @@
-use std::time::{Duration, Instant};
+use std::{collections::HashSet, time::Instant};
 
 use actix::Addr;
 use actix_web_actors::ws;
+use chrono::{NaiveDateTime, Utc};
+use futures::FutureExt;
 
-use super::chat_server::{ChatServer, ClientMessage, Connect, Disconnect};
+use crate::{
+    config::HeartbeatSettings,
+    metrics::metrics,
+    rate_limit::{ConnectionLimiter, RATE_LIMITED_MESSAGE},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info, warn, Span};
+use uuid::Uuid;
+
+use super::chat_server::{
+    ChatCommandError, ChatEvent, ChatRoomError, ChatServer, ClientMessage, Connect, Disconnect,
+    JoinRoom, LeaveRoom, ListMembers, MessageFilter, Moderate, ModerationCommand, ModerationError,
+    Post, Rain, Tip, GLOBAL_ROOM,
+};
 use actix::prelude::*;
 pub struct ChatWs {
+    pub conn_id: u64,
     pub user_id: i32,
+    //* Only authorized users can post, anonymous sockets just read */
+    pub username: Option<String>,
+    pub is_moderator: bool,
+    pub account_created_at: Option<NaiveDateTime>,
+    pub filter: MessageFilter,
     pub hb: Instant,
+    pub heartbeat: HeartbeatSettings,
     pub addr: Addr<ChatServer>,
+    pub rooms: HashSet<String>,
+    pub limiter: ConnectionLimiter,
+    pub span: Span,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ChatCommand {
+    Join {
+        room: String,
+    },
+    Leave {
+        room: String,
+    },
+    Members {
+        room: String,
+    },
+    Message {
+        room: Option<String>,
+        msg: String,
+    },
+    #[serde(skip)]
+    Moderate(ModerationCommand),
+}
+
+impl ChatCommand {
+    //* Plain text is still accepted as a message to the global room */
+    fn parse(text: &str) -> Self {
+        serde_json::from_str::<ChatCommand>(text)
+            .or_else(|_| serde_json::from_str::<ModerationCommand>(text).map(ChatCommand::Moderate))
+            .unwrap_or_else(|_| ChatCommand::Message {
+                room: None,
+                msg: text.to_string(),
+            })
+    }
+
+    //* Name of the rate limit bucket the command counts against */
+    fn name(&self) -> &'static str {
+        match self {
+            ChatCommand::Join { .. } => "join",
+            ChatCommand::Leave { .. } => "leave",
+            ChatCommand::Members { .. } => "members",
+            ChatCommand::Message { msg, .. } if msg.trim_start().starts_with('/') => "command",
+            ChatCommand::Message { .. } => "message",
+            ChatCommand::Moderate(_) => "moderate",
+        }
+    }
 }
 
 impl Actor for ChatWs {
     type Context = ws::WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
+        info!(
+            parent: &self.span,
+            authorized = self.username.is_some(),
+            "Connected"
+        );
+        metrics().ws_connections.with_label_values(&["chat"]).inc();
         self.hb(ctx);
+        self.rooms.insert(GLOBAL_ROOM.to_string());
         self.addr.do_send(Connect {
+            conn_id: self.conn_id,
             id: self.user_id,
+            username: self.username.clone(),
+            is_moderator: self.is_moderator,
             addr: ctx.address().recipient(),
+            events: ctx.address().recipient(),
         })
     }
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
-        self.addr.do_send(Disconnect { id: self.user_id })
+        info!(parent: &self.span, "Disconnected");
+        metrics().ws_connections.with_label_values(&["chat"]).dec();
+        self.addr.do_send(Disconnect {
+            conn_id: self.conn_id,
+        })
     }
 }
 
 impl ChatWs {
     fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        ctx.run_interval(Duration::new(5, 0), |act, ctx| {
-            if Instant::now().duration_since(act.hb) > Duration::from_secs(10) {
+        ctx.run_interval(self.heartbeat.interval(), |act, ctx| {
+            if Instant::now().duration_since(act.hb) > act.heartbeat.timeout() {
                 ctx.stop();
-                return;
             } else {
                 ctx.ping(b"");
             }
         });
     }
+
+    fn join_room(&self, room: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let join = JoinRoom {
+            conn_id: self.conn_id,
+            room: room.clone(),
+        };
+        ctx.spawn(self.addr.send(join).into_actor(self).map(
+            move |result, act, ctx| match result {
+                Ok(Ok(())) => {
+                    act.rooms.insert(room);
+                }
+                result => act.reject(ctx, "join_rejected", &room, result),
+            },
+        ));
+    }
+
+    fn leave_room(&self, room: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let leave = LeaveRoom {
+            conn_id: self.conn_id,
+            room: room.clone(),
+        };
+        ctx.spawn(self.addr.send(leave).into_actor(self).map(
+            move |result, act, ctx| match result {
+                Ok(Ok(())) => {
+                    act.rooms.remove(&room);
+                }
+                result => act.reject(ctx, "leave_rejected", &room, result),
+            },
+        ));
+    }
+
+    fn list_members(&self, room: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let list = ListMembers {
+            conn_id: self.conn_id,
+            room: room.clone(),
+        };
+        ctx.spawn(
+            self.addr
+                .send(list)
+                .into_actor(self)
+                .map(move |result, act, ctx| {
+                    if !matches!(result, Ok(Ok(()))) {
+                        act.reject(ctx, "members_rejected", &room, result);
+                    }
+                }),
+        );
+    }
+
+    fn moderate(&self, command: ModerationCommand, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.username.is_none() {
+            self.reply(
+                ctx,
+                ChatEvent::direct(
+                    "moderation_rejected",
+                    json!({ "reason": ModerationError::NotAllowed.to_string() }),
+                ),
+            );
+            return;
+        }
+        let moderate = Moderate {
+            moderator_id: self.user_id,
+            command,
+        };
+        ctx.spawn(
+            self.addr
+                .send(moderate)
+                .into_actor(self)
+                .map(|result, act, ctx| {
+                    let reason = match result {
+                        Ok(Ok(())) => return,
+                        Ok(Err(e)) => e.to_string(),
+                        Err(_) => "Something went wrong".to_string(),
+                    };
+                    act.reply(
+                        ctx,
+                        ChatEvent::direct("moderation_rejected", json!({ "reason": reason })),
+                    );
+                }),
+        );
+    }
+
+    fn post(&self, room: Option<String>, msg: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let room = room.unwrap_or_else(|| GLOBAL_ROOM.to_string());
+        let Some(username) = &self.username else {
+            return;
+        };
+        if !self.rooms.contains(&room) {
+            self.reply(
+                ctx,
+                ChatEvent::new(
+                    "message_rejected",
+                    &room,
+                    json!({ "reason": ChatRoomError::NotAMember.to_string() }),
+                ),
+            );
+            return;
+        }
+        if let Some(command) = msg.trim_start().strip_prefix('/') {
+            self.slash_command(room, command.to_string(), ctx);
+            return;
+        }
+        let msg = match self.filter.check_message(&msg, self.account_created_at) {
+            Ok(msg) => msg,
+            Err(e) => {
+                self.reply(
+                    ctx,
+                    ChatEvent::new(
+                        "message_rejected",
+                        &room,
+                        json!({ "reason": e.to_string(), "code": e.code() }),
+                    ),
+                );
+                return;
+            }
+        };
+        self.addr.do_send(Post {
+            conn_id: self.conn_id,
+            message: ClientMessage {
+                id: self.user_id,
+                username: username.clone(),
+                msg,
+                message_id: Uuid::new_v4().to_string(),
+                room,
+                created_at: Utc::now(),
+                system: false,
+            },
+        });
+    }
+
+    fn slash_command(&self, room: String, command: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        let request = match args.as_slice() {
+            ["tip", to_username, amount] => amount.parse::<f64>().ok().map(|amount| {
+                self.addr
+                    .send(Tip {
+                        from_id: self.user_id,
+                        room: room.clone(),
+                        to_username: to_username.to_string(),
+                        amount,
+                    })
+                    .boxed_local()
+            }),
+            ["rain", amount] => amount.parse::<f64>().ok().map(|amount| {
+                self.addr
+                    .send(Rain {
+                        from_id: self.user_id,
+                        room: room.clone(),
+                        amount,
+                    })
+                    .boxed_local()
+            }),
+            _ => None,
+        };
+        let Some(request) = request else {
+            let error = match args.first() {
+                Some(&"tip") => ChatCommandError::Usage("/tip <user> <amount>"),
+                Some(&"rain") => ChatCommandError::Usage("/rain <amount>"),
+                _ => ChatCommandError::UnknownCommand,
+            };
+            self.reply(
+                ctx,
+                ChatEvent::new(
+                    "command_rejected",
+                    &room,
+                    json!({ "reason": error.to_string() }),
+                ),
+            );
+            return;
+        };
+
+        ctx.spawn(request.into_actor(self).map(move |result, act, ctx| {
+            let reason = match result {
+                Ok(Ok(())) => return,
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "Something went wrong".to_string(),
+            };
+            act.reply(
+                ctx,
+                ChatEvent::new("command_rejected", &room, json!({ "reason": reason })),
+            );
+        }));
+    }
+
+    fn reject(
+        &self,
+        ctx: &mut ws::WebsocketContext<Self>,
+        variant: &str,
+        room: &str,
+        result: Result<Result<(), ChatRoomError>, MailboxError>,
+    ) {
+        let reason = match result {
+            Ok(Err(e)) => e.to_string(),
+            _ => "Something went wrong".to_string(),
+        };
+        self.reply(
+            ctx,
+            ChatEvent::new(variant, room, json!({ "reason": reason })),
+        );
+    }
+
+    //* Replies to this socket only, unlike server events which go to the whole room */
+    fn reply(&self, ctx: &mut ws::WebsocketContext<Self>, event: ChatEvent) {
+        if let Ok(json_msg) = serde_json::to_string(&event) {
+            ctx.text(json_msg);
+        }
+    }
 }
 
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ChatWs {
@@ -51,14 +341,28 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ChatWs {
                 self.hb = Instant::now();
             }
             Ok(ws::Message::Text(msg)) => {
-                let payload = ClientMessage {
-                    id: self.user_id,
-                    msg: msg.to_string(),
+                let command = ChatCommand::parse(&msg);
+                let user_id = self.username.as_ref().map(|_| self.user_id);
+                let name = command.name();
+                let rate_limited = || {
+                    ChatEvent::direct(
+                        "rate_limited",
+                        json!({ "reason": RATE_LIMITED_MESSAGE, "command": name }),
+                    )
                 };
-                self.addr.do_send(payload);
+                if !self.limiter.throttle(user_id, name, rate_limited, ctx) {
+                    return;
+                }
+                match command {
+                    ChatCommand::Join { room } => self.join_room(room, ctx),
+                    ChatCommand::Leave { room } => self.leave_room(room, ctx),
+                    ChatCommand::Members { room } => self.list_members(room, ctx),
+                    ChatCommand::Message { room, msg } => self.post(room, msg, ctx),
+                    ChatCommand::Moderate(command) => self.moderate(command, ctx),
+                }
             }
             Err(err) => {
-                eprintln!("WebSocket error: {:?}", err);
+                warn!(parent: &self.span, error = %err, "Websocket error");
                 ctx.stop();
             }
             _ => (),
@@ -73,7 +377,15 @@ impl Handler<ClientMessage> for ChatWs {
         if let Ok(json_msg) = serde_json::to_string(&msg) {
             ctx.text(json_msg);
         } else {
-            eprintln!("Failed to serialize message to JSON");
+            error!(parent: &self.span, "Failed to serialize message");
         }
     }
 }
+
+impl Handler<ChatEvent> for ChatWs {
+    type Result = ();
+
+    fn handle(&mut self, msg: ChatEvent, ctx: &mut Self::Context) {
+        self.reply(ctx, msg);
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::{collections::HashSet, time::Instant};
 
 use actix::Addr;
 use actix_web::{
@@ -6,24 +6,72 @@ use actix_web::{
     HttpRequest, HttpResponse, Responder,
 };
 use actix_web_actors::ws;
-use chat_server::ChatServer;
+use chat_server::{ChatServer, MessageFilter};
 use chat_ws::ChatWs;
-use rand::Rng;
+
+use crate::{
+    config::Settings,
+    db_utils::AppState,
+    errors::auth::GetUserError,
+    handlers::websocket::{anonymous_id, connection_id},
+    jwt::decode_jwt,
+    rate_limit::{ConnectionLimiter, RateLimiters},
+    telemetry::connection_span,
+};
 
 pub mod chat_server;
 pub mod chat_ws;
 
 pub async fn handle_chat_ws(
     chat_server: Data<Addr<ChatServer>>,
+    message_filter: Data<MessageFilter>,
+    rate_limiters: Data<RateLimiters>,
+    app_state: Data<AppState>,
+    settings: Data<Settings>,
     req: HttpRequest,
     stream: Payload,
 ) -> impl Responder {
-    let rand = rand::thread_rng().gen_range(1..1000);
+    let token = req
+        .query_string()
+        .split('&')
+        .find(|&param| param.starts_with("token="))
+        .and_then(|param| param.split('=').nth(1));
+
+    let (user_id, username, is_moderator, account_created_at) = match token {
+        Some(tkn) => {
+            let Ok(claims) = decode_jwt(tkn) else {
+                return HttpResponse::Unauthorized().finish();
+            };
+            //* The role is looked up rather than trusted from the token so demotions apply right away */
+            let user = app_state.repos.users.find(claims.claims.sub).await;
+            match user {
+                Ok(user) => (
+                    user.id,
+                    Some(user.username.clone()),
+                    user.can_moderate(),
+                    Some(user.created_at),
+                ),
+                Err(GetUserError::UserNotFound) => return HttpResponse::Unauthorized().finish(),
+                Err(_) => return HttpResponse::InternalServerError().finish(),
+            }
+        }
+        None => (anonymous_id(), None, false, None),
+    };
+
     let res = ws::start(
         ChatWs {
-            user_id: rand,
+            conn_id: connection_id(),
+            user_id,
+            username,
+            is_moderator,
+            account_created_at,
+            filter: message_filter.get_ref().clone(),
+            limiter: ConnectionLimiter::new(rate_limiters.chat.clone()),
             hb: Instant::now(),
+            heartbeat: settings.heartbeat,
             addr: chat_server.get_ref().clone(),
+            rooms: HashSet::new(),
+            span: connection_span("chat", user_id),
         },
         &req,
         stream,
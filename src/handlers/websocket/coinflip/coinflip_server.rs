@@ -1,21 +1,55 @@
 use std::collections::HashMap;
 
-use actix::{Actor, AsyncContext, Context, Handler, Message, Recipient};
+use actix::{fut, Actor, ActorFutureExt, Addr, AsyncContext, Context, Handler, Message, Recipient, ResponseActFuture, WrapFuture};
+use chrono::Utc;
+use futures::{future::{join_all, LocalBoxFuture}, FutureExt};
 use rand::Rng;
 use serde::Serialize;
 use serde_json::json;
+use thiserror::Error;
+use tracing::{debug, error, info, Span};
+
+use crate::{
+    errors::wallet::WalletError,
+    events::{EventBus, GameResult, GAME_COINFLIP},
+    messages::health::Ping,
+    metrics::metrics,
+    models::{
+        round::{STATUS_OPEN, STATUS_REFUNDED, STATUS_SETTLED, STATUS_VOIDED},
+        wallet::{REASON_COINFLIP_BET, REASON_COINFLIP_PAYOUT, REASON_COINFLIP_REFUND},
+    },
+    repositories::{detach, ledger::ApplyLedgerEntry, rounds::{log_recovery, JournalBet, JournalRound}, Repositories},
+    shutdown::{Shutdown, SHUTDOWN_NOTICE},
+    telemetry::round_span,
+};
+
+use super::house_bot::{GameOpened, HouseBot, HouseBotConfig};
 
 
 //* --- Struct --- */
 pub struct CoinflipServer {
     pub spectators: Vec<i32>,
     pub sessions: HashMap<String, CoinflipGame>,
+    pub repos: Repositories,
+    pub house_bot_config: Option<HouseBotConfig>,
+    pub house_bot: Option<Addr<HouseBot>>,
+    pub events: Addr<EventBus>,
+    pub shutting_down: bool,
 }
 impl CoinflipServer{
-    pub fn new() -> Self{
+    pub fn new(
+        repos: Repositories,
+        house_bot_config: Option<HouseBotConfig>,
+        events: Addr<EventBus>,
+    ) -> Self {
         CoinflipServer{
             spectators: Vec::new(),
             sessions: HashMap::new(),
+            repos,
+            house_bot_config,
+            house_bot: None,
+            events,
+            shutting_down: false,
         }
     }
 }
@@ -24,12 +58,14 @@ pub struct CoinflipGame {
     pub spectators: HashMap<i32,Recipient<ClientMessage>>,
     pub players: Vec<Player>,
     pub amount: f64,
+    pub span: Span,
 }
 #[derive(Clone,Debug)]
 pub struct Player {
-    pub id: usize,
+    pub id: i32,
     pub name: String,
     pub addr: Recipient<ClientMessage>,
+    pub is_house: bool,
 }
 #[derive(Serialize)]
 pub struct JsonResponse {
@@ -60,22 +96,60 @@ pub enum ClientMessage {
 
 
 #[derive(Message)]
-#[rtype(result = "()")]
+#[rtype(result = "Result<(),CreateGameError>")]
 pub struct AddGame {
     pub amount: f64,
     pub player: Player,
 }
 
 #[derive(Message,Debug)]
-#[rtype(result = "()")]
+#[rtype(result = "Result<(),JoinGameError>")]
 pub struct JoinGame {
     pub gameid: String,
     pub player: Player,
 }
+
+#[derive(Error, Debug)]
+pub enum CreateGameError {
+    #[error("Amount must be positive")]
+    InvalidAmount,
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    #[error("The server is shutting down")]
+    ShuttingDown,
+    #[error("Internal error")]
+    InternalError,
+}
+
+#[derive(Error, Debug)]
+pub enum JoinGameError {
+    #[error("Game not found")]
+    GameNotFound,
+    #[error("Game is already full")]
+    GameFull,
+    #[error("Player already joined")]
+    AlreadyJoined,
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    #[error("The server is shutting down")]
+    ShuttingDown,
+    #[error("Internal error")]
+    InternalError,
+}
 //* X */
 //* --- Actor --- */
 impl Actor for CoinflipServer {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let recover = self.repos.rounds.recover(GAME_COINFLIP.to_string(), Utc::now().naive_utc());
+        ctx.spawn(recover.into_actor(self).map(|result, _act, _ctx| log_recovery(GAME_COINFLIP, result)));
+
+        if let Some(config) = self.house_bot_config.clone() {
+            let house_bot = HouseBot::new(config, ctx.address()).start();
+            self.house_bot = Some(house_bot);
+        }
+    }
 }
 impl Actor for CoinflipGame {
     type Context = Context<Self>;
@@ -151,57 +225,296 @@ impl Handler<ClientMessage> for CoinflipServer {
     }
 }
 impl Handler<AddGame> for CoinflipServer {
-    type Result = ();
-    fn handle(&mut self, msg: AddGame, ctx: &mut Self::Context) -> Self::Result {
+    type Result = ResponseActFuture<Self, Result<(), CreateGameError>>;
+    fn handle(&mut self, msg: AddGame, _ctx: &mut Self::Context) -> Self::Result {
+        if self.shutting_down {
+            return Box::pin(fut::ready(Err(CreateGameError::ShuttingDown)));
+        }
+        let amount = round_to(msg.amount, 2);
+        if amount.is_nan() || amount <= 0.0 {
+            return Box::pin(fut::ready(Err(CreateGameError::InvalidAmount)));
+        }
         let id = uuid::Uuid::new_v4().to_string();
-        let new_game = CoinflipGame::new(msg.amount, msg.player.clone());
+        let journal = self.repos.rounds.journal_round(JournalRound {
+            game: GAME_COINFLIP.to_string(),
+            round_id: id.clone(),
+            status: STATUS_OPEN.to_string(),
+        });
+        let ledger = self.repos.ledger.clone();
+        let entry = ApplyLedgerEntry {
+            user_id: msg.player.id,
+            amount: -amount,
+            reason: REASON_COINFLIP_BET.to_string(),
+            reference: Some(id.clone()),
+        };
+
+        //* The game only takes a stake once it is in the journal, startup recovery refunds whatever it finds open */
+        let debit = async move {
+            if let Err(e) = journal.await {
+                error!(error = %e, "Failed to journal coinflip game");
+                return Err(CreateGameError::InternalError);
+            }
+            ledger.apply(entry).await.map_err(|e| match e {
+                WalletError::InsufficientFunds => CreateGameError::InsufficientFunds,
+                _ => CreateGameError::InternalError,
+            })
+        };
+
+        Box::pin(debit.into_actor(self).map(move |result, act, ctx| {
+            if let Err(e) = result {
+                act.journal(&id, STATUS_VOIDED);
+                return Err(e);
+            }
+            if act.shutting_down {
+                let game = CoinflipGame::new(&id, amount, msg.player);
+                ctx.spawn(act.refund_game(id, &game).into_actor(act));
+                return Err(CreateGameError::ShuttingDown);
+            }
+            act.open_game(id, amount, msg.player, ctx);
+            Ok(())
+        }))
+    }
+}
+
+impl Handler<JoinGame> for CoinflipServer {
+    type Result = ResponseActFuture<Self, Result<(), JoinGameError>>;
+
+    fn handle(&mut self, msg: JoinGame, _ctx: &mut Self::Context) -> Self::Result {
+        let amount = match self.check_join(&msg.gameid, msg.player.id) {
+            Ok(amount) => amount,
+            Err(e) => return Box::pin(fut::ready(Err(e))),
+        };
+        let debit = self.repos.ledger.apply(ApplyLedgerEntry {
+            user_id: msg.player.id,
+            amount: -amount,
+            reason: REASON_COINFLIP_BET.to_string(),
+            reference: Some(msg.gameid.clone()),
+        });
+
+        Box::pin(debit.into_actor(self).map(move |result, act, ctx| {
+            match result {
+                Ok(_) => (),
+                Err(WalletError::InsufficientFunds) => return Err(JoinGameError::InsufficientFunds),
+                Err(_) => return Err(JoinGameError::InternalError),
+            }
+            //* Someone else may have taken the seat while the stake was debited */
+            if let Err(e) = act.check_join(&msg.gameid, msg.player.id) {
+                detach("apply_ledger_entry", act.repos.ledger.apply(ApplyLedgerEntry {
+                    user_id: msg.player.id,
+                    amount,
+                    reason: REASON_COINFLIP_REFUND.to_string(),
+                    reference: Some(msg.gameid.clone()),
+                }));
+                return Err(e);
+            }
+            act.seat_player(msg.gameid, msg.player, ctx);
+            Ok(())
+        }))
+    }
+}
+
+//* Open games are cancelled and every stake in them refunded */
+impl Handler<Shutdown> for CoinflipServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: Shutdown, _ctx: &mut Self::Context) -> Self::Result {
+        self.shutting_down = true;
+        let mut refunds = Vec::new();
+        for (game_id, game) in std::mem::take(&mut self.sessions) {
+            info!(parent: &game.span, "Game refunded");
+            refunds.push(self.refund_game(game_id.clone(), &game));
+            let cancelled = JsonResponse {
+                message_type: String::from("game_cancelled"),
+                payload: json!({
+                    "game_id": game_id,
+                    "amount": game.amount,
+                    "msg": SHUTDOWN_NOTICE,
+                }),
+            };
+            let Ok(cancelled) = serde_json::to_string(&cancelled) else {
+                continue;
+            };
+            for player in &game.players {
+                player.addr.do_send(ClientMessage::Text(cancelled.clone()));
+            }
+            for spectator in game.spectators.values() {
+                spectator.do_send(ClientMessage::Text(cancelled.clone()));
+            }
+        }
+        Box::pin(join_all(refunds).into_actor(self).map(|_, _, _| ()))
+    }
+}
+
+impl Handler<Ping> for CoinflipServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+//* --- X --- */
+//* Implementations */
+impl CoinflipServer {
+    fn journal(&self, game_id: &str, status: &str) {
+        detach("journal_round", self.repos.rounds.journal_round(JournalRound {
+            game: GAME_COINFLIP.to_string(),
+            round_id: game_id.to_string(),
+            status: status.to_string(),
+        }));
+    }
+
+    //* The stake a player has to put up to take the free seat */
+    fn check_join(&self, game_id: &str, user_id: i32) -> Result<f64, JoinGameError> {
+        if self.shutting_down {
+            return Err(JoinGameError::ShuttingDown);
+        }
+        let game = self
+            .sessions
+            .get(game_id)
+            .ok_or(JoinGameError::GameNotFound)?;
+        if game.players.len() >= 2 {
+            return Err(JoinGameError::GameFull);
+        }
+        if game.players.iter().any(|p| p.id == user_id) {
+            return Err(JoinGameError::AlreadyJoined);
+        }
+        Ok(game.amount)
+    }
+
+    fn open_game(&mut self, id: String, amount: f64, player: Player, ctx: &mut Context<Self>) {
+        let new_game = CoinflipGame::new(&id, amount, player.clone());
+        info!(parent: &new_game.span, creator_id = player.id, amount, "Game opened");
         self.sessions.insert(id.clone(), new_game);
-        println!("{:?}",self.sessions);
+        metrics().record_bet(GAME_COINFLIP, amount);
+        detach("journal_bet", self.repos.rounds.journal_bet(JournalBet {
+            round_id: id.clone(),
+            user_id: player.id,
+            amount,
+        }));
+        if let Some(house_bot) = &self.house_bot {
+            house_bot.do_send(GameOpened {
+                game_id: id.clone(),
+                amount,
+            });
+        }
         ctx.address().do_send(ClientMessage::Json(JsonResponse{
             message_type:String::from("new_game"),
             payload: json!({
                 "game_id": id,
-                "amount": msg.amount,
+                "amount": amount,
                 "player": {
-                    "id": msg.player.id,
-                    "name": msg.player.name,
+                    "id": player.id,
+                    "name": player.name,
                 }
             })
         }));
     }
-}
 
-impl Handler<JoinGame> for CoinflipServer {
-    type Result = ();
+    fn seat_player(&mut self, game_id: String, player: Player, ctx: &mut Context<Self>) {
+        let Some(game) = self.sessions.get_mut(&game_id) else {
+            return;
+        };
+        detach("journal_bet", self.repos.rounds.journal_bet(JournalBet {
+            round_id: game_id.clone(),
+            user_id: player.id,
+            amount: game.amount,
+        }));
+        debug!(parent: &game.span, user_id = player.id, "Player joined");
+        metrics().record_bet(GAME_COINFLIP, game.amount);
+        game.players.push(player);
 
-    fn handle(&mut self, msg: JoinGame, _ctx: &mut Self::Context) -> Self::Result {
-        println!("{:?}",msg);
-        println!("{:?}",self.sessions);
-        if let Some(game) = self.sessions.get_mut(&msg.gameid) {
-            println!("{:?}",game);
-            if game.players.iter().all(|p| p.id != msg.player.id) {
-                game.players.insert(msg.player.id, msg.player);
-                println!("user joined");
-                if game.players.len() == 2 {
-                    game.start_game();
+        if game.players.len() == 2 {
+            if let Some(game) = self.sessions.remove(&game_id) {
+                self.settle(game_id, game, ctx);
+            }
+        }
+    }
+
+    //* The game is only journaled as settled once the winner has been paid, otherwise startup recovery refunds it */
+    fn settle(&mut self, game_id: String, mut game: CoinflipGame, ctx: &mut Context<Self>) {
+        let winner = game.start_game();
+        let payout = game.amount * 2.0;
+        info!(parent: &game.span, winner_id = winner.id, amount = game.amount, "Game settled");
+        metrics().record_round(GAME_COINFLIP);
+        if game.players.iter().any(|p| p.is_house) {
+            let profit = if winner.is_house { game.amount } else { -game.amount };
+            metrics().record_house_profit(GAME_COINFLIP, profit);
+        }
+        let credit = self.repos.ledger.apply(ApplyLedgerEntry {
+            user_id: winner.id,
+            amount: payout,
+            reason: REASON_COINFLIP_PAYOUT.to_string(),
+            reference: Some(game_id.clone()),
+        });
+        ctx.spawn(credit.into_actor(self).map(move |result, act, _ctx| {
+            if let Err(e) = result {
+                error!(
+                    parent: &game.span,
+                    round_id = %game_id,
+                    user_id = winner.id,
+                    amount = payout,
+                    error = %e,
+                    "Failed to pay the winner"
+                );
+                return;
+            }
+            act.journal(&game_id, STATUS_SETTLED);
+            metrics().record_payout(GAME_COINFLIP, payout);
+            if !winner.is_house {
+                act.events.do_send(GameResult {
+                    game: GAME_COINFLIP.to_string(),
+                    round_id: game_id,
+                    user_id: winner.id,
+                    username: winner.name,
+                    stake: game.amount,
+                    payout,
+                });
+            }
+        }));
+    }
+
+    //* A stake that could not be refunded leaves the game open, so startup recovery refunds it */
+    fn refund_game(&self, game_id: String, game: &CoinflipGame) -> LocalBoxFuture<'static, ()> {
+        let repos = self.repos.clone();
+        let user_ids: Vec<i32> = game.players.iter().map(|p| p.id).collect();
+        let amount = game.amount;
+        let span = game.span.clone();
+        async move {
+            let mut refunded = true;
+            for user_id in user_ids {
+                let refund = repos.ledger.apply(ApplyLedgerEntry {
+                    user_id,
+                    amount,
+                    reason: REASON_COINFLIP_REFUND.to_string(),
+                    reference: Some(game_id.clone()),
+                });
+                if let Err(e) = refund.await {
+                    error!(parent: &span, round_id = %game_id, user_id, amount, error = %e, "Failed to refund stake");
+                    refunded = false;
                 }
             }
+            if refunded {
+                detach("journal_round", repos.rounds.journal_round(JournalRound {
+                    game: GAME_COINFLIP.to_string(),
+                    round_id: game_id,
+                    status: STATUS_REFUNDED.to_string(),
+                }));
+            }
         }
+        .boxed_local()
     }
 }
 
-//* --- X --- */
-//* Implementations */
 impl CoinflipGame {
-    fn new(amount: f64, player: Player) -> Self {
+    fn new(game_id: &str, amount: f64, player: Player) -> Self {
         let rounded_amount = round_to(amount, 2);
         CoinflipGame {
             spectators: HashMap::new(),
             players: vec![player],
             amount: rounded_amount,
+            span: round_span(GAME_COINFLIP, game_id),
         }
     }
-    fn start_game(&mut self) {
+    fn start_game(&mut self) -> Player {
         let rand_num = rand::thread_rng().gen_range(0..2);
 
         let winner = &self.players[rand_num];
@@ -219,6 +532,8 @@ impl CoinflipGame {
         for spectator in self.spectators.values() {
             spectator.do_send(ClientMessage::Text(game_result_msg.clone()));
         }
+
+        winner.clone()
     }
 }
 
@@ -1,26 +1,36 @@
-use std::time::Duration;
-
 use actix::ActorContext;
+use actix::ActorFutureExt;
 use actix::Addr;
 use actix::AsyncContext;
 use actix::Handler;
 use actix::StreamHandler;
 use actix::{clock::Instant, Actor};
+use actix::WrapFuture;
 use actix_web_actors::ws;
 use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn, Span};
 
 
 use super::coinflip_server::AddGame;
 use super::coinflip_server::JoinGame;
 use super::coinflip_server::Player;
-use super::coinflip_server::{CoinflipServer,Connect,ClientMessage,Disconnect};
+use super::coinflip_server::{CoinflipServer,Connect,ClientMessage,Disconnect,JsonResponse};
+use crate::config::HeartbeatSettings;
+use crate::events::GAME_COINFLIP;
+use crate::handlers::websocket::LOGIN_REQUIRED;
+use crate::metrics::metrics;
+use crate::rate_limit::{ConnectionLimiter, RATE_LIMITED_MESSAGE};
 
 pub struct CoinflipWs {
     pub session_id: String,
     pub addr: Addr<CoinflipServer>,
     pub hb: Instant,
+    pub heartbeat: HeartbeatSettings,
     pub user_id: i32,
     pub name: Option<String>,
+    pub limiter: ConnectionLimiter,
+    pub span: Span,
 }
 
 #[derive(Deserialize)]
@@ -31,13 +41,11 @@ pub struct RequestPayload{
 
 #[derive(Deserialize,Debug)]
 pub struct JoinPayload{
-    user_id:usize,
     game_id:String
 }
 
 #[derive(Deserialize)]
 pub struct CreateGamePayload{
-    user_id:usize,
     amount:usize
 }
 
@@ -47,7 +55,8 @@ impl Actor for CoinflipWs {
     type Context = ws::WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        println!("User with id: {} Connecting", self.user_id);
+        info!(parent: &self.span, "Connected");
+        metrics().ws_connections.with_label_values(&[GAME_COINFLIP]).inc();
         self.hb(ctx);
         self.addr.do_send(Connect{
             user_id: self.user_id,
@@ -57,6 +66,8 @@ impl Actor for CoinflipWs {
     }
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
+        info!(parent: &self.span, "Disconnected");
+        metrics().ws_connections.with_label_values(&[GAME_COINFLIP]).dec();
         self.addr.do_send(Disconnect{
             user_id:self.user_id,
             session_id: self.session_id.clone()
@@ -68,28 +79,44 @@ impl StreamHandler<Result<ws::Message,ws::ProtocolError>> for CoinflipWs{
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         match msg{
             Ok(ws::Message::Text(txt))=>{
-                if let Ok(msg_type) = serde_json::from_str::<RequestPayload>(&txt){
+                let parsed = serde_json::from_str::<RequestPayload>(&txt);
+                let command = parsed.as_ref().map(|req| req.msg_type.clone()).unwrap_or_else(|_| "invalid".to_string());
+                let user_id = self.name.as_ref().map(|_| self.user_id);
+                let rate_limited = || JsonResponse {
+                    message_type: "rate_limited".to_string(),
+                    payload: json!({ "msg": RATE_LIMITED_MESSAGE }),
+                };
+                if !self.limiter.throttle(user_id, &command, rate_limited, ctx){
+                    return;
+                }
+                if let Ok(msg_type) = parsed{
                     match msg_type.msg_type.as_str(){
                         "join"=>{
                             let payload = serde_json::from_value::<JoinPayload>(msg_type.payload);
-                            println!("{:?}",payload);
                             match payload{
                                 Ok(user) => {
                                 if let Some(name) = self.name.clone(){
                                     let new_player = Player{
-                                        id:user.user_id,
+                                        id:self.user_id,
                                         name,
-                                        addr: ctx.address().recipient()
+                                        addr: ctx.address().recipient(),
+                                        is_house: false
                                     };
-                                    self.addr.do_send(JoinGame{
+                                    let join = self.addr.send(JoinGame{
                                         player:new_player,
                                         gameid:user.game_id.clone()
-                                    })
+                                    });
+                                    ctx.spawn(join.into_actor(self).map(|result, act, ctx| match result {
+                                        Ok(Ok(())) => (),
+                                        Ok(Err(e)) => act.reject("join_rejected", &e.to_string(), ctx),
+                                        Err(_) => act.reject("join_rejected", "Something went wrong", ctx),
+                                    }));
+                                } else {
+                                    self.reject("join_rejected", LOGIN_REQUIRED, ctx);
                                 }
                                 },
                                 Err(_) => {
-                                    println!("Failed to deserialize JoinPayload");
-                                    return;
+                                    warn!(parent: &self.span, "Invalid join payload");
                                 }
  
                             }
@@ -100,19 +127,26 @@ impl StreamHandler<Result<ws::Message,ws::ProtocolError>> for CoinflipWs{
                                 Ok(user) => {
                                     if let Some(name) = self.name.clone(){
                                         let new_player = Player{
-                                            id:user.user_id,
+                                            id:self.user_id,
                                             name,
-                                            addr: ctx.address().recipient()
+                                            addr: ctx.address().recipient(),
+                                            is_house: false
                                         };
-                                        self.addr.do_send(AddGame{
+                                        let create = self.addr.send(AddGame{
                                             player:new_player,
                                             amount:user.amount as f64
-                                        })
+                                        });
+                                        ctx.spawn(create.into_actor(self).map(|result, act, ctx| match result {
+                                            Ok(Ok(())) => (),
+                                            Ok(Err(e)) => act.reject("create_rejected", &e.to_string(), ctx),
+                                            Err(_) => act.reject("create_rejected", "Something went wrong", ctx),
+                                        }));
+                                    } else {
+                                        self.reject("create_rejected", LOGIN_REQUIRED, ctx);
                                     }
                                 },
                                 Err(_) => {
-                                    println!("Failed to deserialize create game payload");
-                                    return;
+                                    warn!(parent: &self.span, "Invalid create game payload");
                                 }
                             }
                         }
@@ -129,9 +163,8 @@ impl StreamHandler<Result<ws::Message,ws::ProtocolError>> for CoinflipWs{
                 self.hb = Instant::now();
             },
             Err(e)=>{
-                println!("websocket error: {:?}", e);
+                warn!(parent: &self.span, error = %e, "Websocket error");
                 ctx.stop();
-                return;
             },
             _=>()
         }
@@ -139,11 +172,20 @@ impl StreamHandler<Result<ws::Message,ws::ProtocolError>> for CoinflipWs{
 }
 
 impl CoinflipWs {
+    fn reject(&self, message_type: &str, reason: &str, ctx: &mut ws::WebsocketContext<Self>) {
+        let reply = JsonResponse {
+            message_type: message_type.to_string(),
+            payload: json!({ "msg": reason }),
+        };
+        if let Ok(json_msg) = serde_json::to_string(&reply) {
+            ctx.text(json_msg);
+        }
+    }
+
     fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        ctx.run_interval(Duration::from_secs(5), |act, ctx| {
-            if Instant::now().duration_since(act.hb) > Duration::from_secs(10) {
+        ctx.run_interval(self.heartbeat.interval(), |act, ctx| {
+            if Instant::now().duration_since(act.hb) > act.heartbeat.timeout() {
                 ctx.stop();
-                return;
             } else {
                 ctx.ping(b"");
             }
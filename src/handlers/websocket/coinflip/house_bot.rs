@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use actix::{Actor, ActorFutureExt, Addr, AsyncContext, Context, Handler, Message, WrapFuture};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::CoinflipSettings, events::GAME_COINFLIP, models::user::User, telemetry::round_span,
+};
+
+use super::coinflip_server::{ClientMessage, CoinflipServer, JoinGame, Player};
+
+//* --- Struct --- */
+#[derive(Clone, Debug)]
+pub struct HouseBotConfig {
+    pub account_id: i32,
+    pub account_name: String,
+    pub join_delay: Duration,
+    pub min_amount: f64,
+    pub max_amount: f64,
+}
+
+impl HouseBotConfig {
+    //* The bot is opt-in: it only runs when coinflip.house_bot is set and the house account could be loaded */
+    pub fn from_settings(settings: &CoinflipSettings, house: Option<&User>) -> Option<Self> {
+        if !settings.house_bot {
+            return None;
+        }
+        let Some(house) = house else {
+            warn!("Coinflip house bot disabled: could not load house account");
+            return None;
+        };
+
+        Some(HouseBotConfig {
+            account_id: house.id,
+            account_name: house.username.clone(),
+            join_delay: settings.house_bot_delay(),
+            min_amount: settings.house_bot_min_amount,
+            max_amount: settings.house_bot_max_amount,
+        })
+    }
+
+    pub fn accepts(&self, amount: f64) -> bool {
+        amount >= self.min_amount && amount <= self.max_amount
+    }
+}
+
+pub struct HouseBot {
+    config: HouseBotConfig,
+    server: Addr<CoinflipServer>,
+}
+//* --- X --- */
+//* --- Messages --- */
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct GameOpened {
+    pub game_id: String,
+    pub amount: f64,
+}
+//* --- X --- */
+//* --- Actor --- */
+impl Actor for HouseBot {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        info!(
+            user_id = self.config.account_id,
+            "Coinflip house bot enabled"
+        );
+    }
+}
+//* --- X --- */
+//* --- Handler --- */
+impl Handler<GameOpened> for HouseBot {
+    type Result = ();
+
+    fn handle(&mut self, msg: GameOpened, ctx: &mut Self::Context) -> Self::Result {
+        if !self.config.accepts(msg.amount) {
+            return;
+        }
+        ctx.run_later(self.config.join_delay, move |act, ctx| {
+            act.join_game(msg.game_id, ctx);
+        });
+    }
+}
+
+//* The bot takes a seat as a regular player, so it has to accept the messages sent to players */
+impl Handler<ClientMessage> for HouseBot {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ClientMessage, _ctx: &mut Self::Context) -> Self::Result {}
+}
+//* --- X --- */
+//* Implementations */
+impl HouseBot {
+    pub fn new(config: HouseBotConfig, server: Addr<CoinflipServer>) -> Self {
+        HouseBot { config, server }
+    }
+
+    fn join_game(&mut self, game_id: String, ctx: &mut Context<Self>) {
+        let player = Player {
+            id: self.config.account_id,
+            name: self.config.account_name.clone(),
+            addr: ctx.address().recipient(),
+            is_house: true,
+        };
+        let join = self.server.send(JoinGame {
+            gameid: game_id.clone(),
+            player,
+        });
+
+        //* The stake goes through the server like any player's, a game someone else took is simply skipped */
+        ctx.spawn(join.into_actor(self).map(move |result, _act, _ctx| {
+            let span = round_span(GAME_COINFLIP, &game_id);
+            match result {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => debug!(parent: &span, reason = %e, "House bot did not join"),
+                Err(e) => warn!(parent: &span, error = %e, "House bot could not reach the server"),
+            }
+        }));
+    }
+}
@@ -3,25 +3,52 @@ use actix_web::{web::{Data, Payload}, HttpRequest, HttpResponse, Responder};
 use actix_web_actors::ws;
 use coinflip_server::CoinflipServer;
 use coinflip_ws::CoinflipWs;
-use rand::Rng;
+use tracing::warn;
+
+use crate::config::Settings;
+use crate::events::GAME_COINFLIP;
+use crate::handlers::websocket::anonymous_id;
+use crate::jwt::decode_jwt;
+use crate::rate_limit::{ConnectionLimiter, RateLimiters};
+use crate::telemetry::connection_span;
 
 pub mod coinflip_server;
 pub mod coinflip_ws;
+pub mod house_bot;
 
 
 pub async fn handle_coinflip_ws(
     stream: Payload,
     server: Data<Addr<CoinflipServer>>,
+    rate_limiters: Data<RateLimiters>,
+    settings: Data<Settings>,
     req: HttpRequest
 ) -> impl Responder{
-    let name = uuid::Uuid::new_v4().to_string();
-    let user_id = rand::thread_rng().gen_range(0..2001);
+    let token = req
+        .query_string()
+        .split('&')
+        .find(|&param| param.starts_with("token="))
+        .and_then(|param| param.split('=').nth(1));
+    //* Players are who their token says, sockets without one can only watch */
+    let (user_id, name) = match token {
+        Some(tkn) => match decode_jwt(tkn) {
+            Ok(claims) => (claims.claims.sub, Some(claims.claims.username)),
+            Err(_) => {
+                warn!("Rejected websocket with an invalid token");
+                return HttpResponse::Unauthorized().finish();
+            }
+        },
+        None => (anonymous_id(), None),
+    };
     let res = ws::start(CoinflipWs{
         addr: server.get_ref().clone(),
         hb: Instant::now(),
+        heartbeat: settings.heartbeat,
         session_id: req.match_info().query("session_id").to_string(),
-        name:Some(name),
-        user_id
+        name,
+        user_id,
+        limiter: ConnectionLimiter::new(rate_limiters.coinflip.clone()),
+        span: connection_span(GAME_COINFLIP, user_id),
     }, &req, stream);
     match res{
         Ok(response) => response,
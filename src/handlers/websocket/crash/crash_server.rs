@@ -1,35 +1,75 @@
-use std::{collections::HashMap, sync::Arc, time::Duration, u32};
+use std::collections::HashMap;
 
 use actix::ActorFutureExt;
 use actix::{
-    clock::Instant, Actor, Addr, AsyncContext, Context, Handler, Message, Recipient, WrapFuture,
+    clock::Instant, fut, Actor, Addr, AsyncContext, Context, Handler, Message, Recipient,
+    ResponseActFuture, SpawnHandle, WrapFuture,
 };
+use chrono::Utc;
+use futures::channel::oneshot;
 use rand::{rngs::OsRng, Rng};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
+use tracing::{debug, error, info, trace, warn, Span};
 use uuid::Uuid;
+
+use crate::{
+    config::CrashSettings,
+    events::GAME_CRASH,
+    messages::health::Ping,
+    metrics::metrics,
+    models::round::{STATUS_RUNNING, STATUS_SETTLED},
+    repositories::{
+        detach,
+        rounds::{log_recovery, JournalBet, JournalRound},
+        Repositories,
+    },
+    shutdown::{Shutdown, SHUTDOWN_NOTICE},
+    telemetry::round_span,
+};
+
+//* Picks the crash point of each round in place of the seeded roll, so a round of known length can be played */
+pub type CrashPoints = Box<dyn FnMut() -> f64>;
+
 pub struct CrashServer {
     pub sessions: HashMap<i32, Recipient<ClientMessage>>,
     pub crash_game: Option<Addr<CrashGame>>,
+    pub settings: CrashSettings,
+    pub repos: Repositories,
+    crash_points: Option<CrashPoints>,
 }
 
 impl Actor for CrashServer {
     type Context = Context<Self>;
     fn started(&mut self, _ctx: &mut Self::Context) {
-        println!("Starting crash");
-        let crash_game = CrashGame::new(_ctx.address()).start();
+        let crash_game = CrashGame::new(
+            _ctx.address(),
+            self.settings,
+            self.repos.clone(),
+            self.crash_points.take(),
+        )
+        .start();
         self.crash_game = Some(crash_game);
     }
 }
 
 impl CrashServer {
-    pub fn new() -> Self {
+    pub fn new(repos: Repositories, settings: CrashSettings) -> Self {
         CrashServer {
+            repos,
+            settings,
             sessions: HashMap::new(),
             crash_game: None,
+            crash_points: None,
         }
     }
+
+    #[cfg(any(test, feature = "test-hooks"))]
+    pub fn with_crash_points(mut self, crash_points: CrashPoints) -> Self {
+        self.crash_points = Some(crash_points);
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -92,8 +132,12 @@ impl Handler<DepositInCrash> for CrashServer {
                         msg_type: "failed_to_deposit".to_string(),
                         msg: "Game already started".to_string(),
                     },
+                    Ok(Err(DepositInCrashError::ShuttingDown)) => ClientMessageJson {
+                        msg_type: "failed_to_deposit".to_string(),
+                        msg: "The server is shutting down".to_string(),
+                    },
                     Err(_) => {
-                        println!("Failed to deposit in crash");
+                        warn!(user_id, "Failed to deposit in crash");
                         ClientMessageJson {
                             msg_type: "failed_to_deposit".to_string(),
                             msg: "Failed to deposit into crash game; game is already started"
@@ -115,7 +159,6 @@ impl Handler<CashOut> for CrashServer {
     fn handle(&mut self, msg: CashOut, ctx: &mut Self::Context) -> Self::Result {
         if let Some(game) = &self.crash_game {
             let clone = game.clone();
-            println!("{:?}", clone);
             let session_clone = self.sessions.clone();
             ctx.spawn(
                 async move {
@@ -126,7 +169,6 @@ impl Handler<CashOut> for CrashServer {
                         .await;
                     match res {
                         Ok(Ok(_)) => {
-                            println!("Cashed out from crash game");
                             let json = ClientMessageJson {
                                 msg_type: "success_cashout".to_string(),
                                 msg: "Cashed out successfully".to_string(),
@@ -188,13 +230,12 @@ impl Handler<ClientMessage> for CrashServer {
                 }
             }
             ClientMessage::Json(json_msg) => {
-                println!("Received JSON message: {}", json_msg.msg);
                 if let Ok(json_msg_str) = serde_json::to_string(&json_msg) {
                     for addr in self.sessions.values() {
                         addr.do_send(ClientMessage::Text(json_msg_str.clone()));
                     }
                 } else {
-                    println!("Failed to serialize JSON message");
+                    error!("Failed to serialize message");
                 }
             }
         }
@@ -232,11 +273,20 @@ pub struct CrashGame {
     crashed: bool,
     started_at: Option<Instant>,
     round_id: Option<String>,
+    round_span: Span,
     public_seed: Option<String>,
     private_seed: Option<String>,
     interval_active: bool,
+    //* The tick of the running round, cancelled once it crashes */
+    ticker: Option<SpawnHandle>,
+    shutting_down: bool,
+    //* Set while a shutdown waits for the running round to crash */
+    round_finished: Option<oneshot::Sender<()>>,
 
     server_addr: Addr<CrashServer>,
+    settings: CrashSettings,
+    repos: Repositories,
+    crash_points: Option<CrashPoints>,
 }
 
 #[derive(Message)]
@@ -264,12 +314,17 @@ pub enum CashoutFromCrashError {
 pub enum DepositInCrashError {
     #[error("Game is already started")]
     GameAlreadyStarted,
+    #[error("The server is shutting down")]
+    ShuttingDown,
 }
 
 impl Handler<AddPlayerToCrash> for CrashGame {
     type Result = Result<(), DepositInCrashError>;
     fn handle(&mut self, msg: AddPlayerToCrash, _ctx: &mut Self::Context) -> Self::Result {
-        if !self.interval_active {
+        if self.shutting_down {
+            Err(DepositInCrashError::ShuttingDown)
+        } else if !self.interval_active {
+            debug!(user_id = msg.user_id, amount = msg.bet_amount, "Bet placed");
             let new_player = Bet {
                 user_id: msg.user_id,
                 bet_amount: msg.bet_amount,
@@ -287,12 +342,11 @@ impl Handler<CashOutFromCrash> for CrashGame {
 
     fn handle(&mut self, msg: CashOutFromCrash, _ctx: &mut Self::Context) -> Self::Result {
         if self.interval_active {
-            println!("players: {:?}", self.players);
-            println!("User id: {}", msg.user_id);
-
             if let Some(player) = self.players.iter_mut().find(|p| p.user_id == msg.user_id) {
                 if !player.cashed_out {
                     player.cashed_out = true;
+                    let (span, multiplier) = (&self.round_span, self.multiplier);
+                    info!(parent: span, user_id = player.user_id, multiplier, "Cashed out");
                     Ok(())
                 } else {
                     Err(CashoutFromCrashError::AlreadyCashedOut)
@@ -312,25 +366,110 @@ pub struct ResetCrashGame;
 impl Handler<ResetCrashGame> for CrashGame {
     type Result = ();
     fn handle(&mut self, _msg: ResetCrashGame, ctx: &mut Self::Context) -> Self::Result {
-        ctx.run_later(Duration::from_secs(15), |act, ctx| {
-            act.reset_game();
-            act.run_game_loop(ctx)
-        });
+        if let Some(round_id) = &self.round_id {
+            detach(
+                "journal_round",
+                self.repos.rounds.journal_round(JournalRound {
+                    game: GAME_CRASH.to_string(),
+                    round_id: round_id.clone(),
+                    status: STATUS_SETTLED.to_string(),
+                }),
+            );
+        }
+        //* Cleared right away so bets placed during the cooldown go to the next round */
+        self.reset_game();
+        if self.shutting_down {
+            if let Some(round_finished) = self.round_finished.take() {
+                let _ = round_finished.send(());
+            }
+            return;
+        }
+        ctx.run_later(self.settings.cooldown(), |act, ctx| act.run_game_loop(ctx));
+    }
+}
+//* A running round is played out, bets placed for the next one are voided */
+impl Handler<Shutdown> for CrashGame {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: Shutdown, _ctx: &mut Self::Context) -> Self::Result {
+        self.shutting_down = true;
+        if !self.interval_active {
+            if !self.players.is_empty() {
+                self.players.clear();
+                self.server_addr
+                    .do_send(ClientMessage::Json(ClientMessageJson {
+                        msg_type: "round_voided".to_string(),
+                        msg: "The next round was cancelled, bets are void".to_string(),
+                    }));
+            }
+            return Box::pin(fut::ready(()));
+        }
+        let (round_finished, finished) = oneshot::channel();
+        self.round_finished = Some(round_finished);
+        Box::pin(finished.into_actor(self).map(|_, _, _| ()))
     }
 }
+
+impl Handler<Shutdown> for CrashServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: Shutdown, _ctx: &mut Self::Context) -> Self::Result {
+        let settled = match &self.crash_game {
+            Some(game) => game
+                .send(Shutdown)
+                .into_actor(self)
+                .map(|_, _, _| ())
+                .boxed_local(),
+            None => Box::pin(fut::ready(())),
+        };
+        Box::pin(settled.map(|_, act, _| {
+            let notice = ClientMessageJson {
+                msg_type: "shutdown".to_string(),
+                msg: SHUTDOWN_NOTICE.to_string(),
+            };
+            if let Ok(notice) = serde_json::to_string(&notice) {
+                for addr in act.sessions.values() {
+                    addr.do_send(ClientMessage::Text(notice.clone()));
+                }
+            }
+        }))
+    }
+}
+
+impl Handler<Ping> for CrashServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
 impl Actor for CrashGame {
     type Context = Context<Self>;
 
     fn started(&mut self, _ctx: &mut Self::Context) {
-        println!("Crash loop starting");
+        //* Crash bets never touch the wallet, so unfinished rounds are only voided */
+        let recover = self
+            .repos
+            .rounds
+            .recover(GAME_CRASH.to_string(), Utc::now().naive_utc());
+        _ctx.spawn(
+            recover
+                .into_actor(self)
+                .map(|result, _act, _ctx| log_recovery(GAME_CRASH, result)),
+        );
+
         self.game_started = true;
         self.run_game_loop(_ctx);
-        println!("Crash loop started");
+        info!("Crash game loop started");
     }
 }
 
 impl CrashGame {
-    fn new(addr: Addr<CrashServer>) -> Self {
+    fn new(
+        addr: Addr<CrashServer>,
+        settings: CrashSettings,
+        repos: Repositories,
+        crash_points: Option<CrashPoints>,
+    ) -> Self {
         Self {
             game_started: false,
             players: Vec::new(),
@@ -339,10 +478,17 @@ impl CrashGame {
             crash_point: None,
             started_at: None,
             round_id: None,
+            round_span: Span::none(),
             public_seed: None,
             private_seed: None,
             interval_active: false,
+            ticker: None,
+            shutting_down: false,
+            round_finished: None,
             server_addr: addr,
+            settings,
+            repos,
+            crash_points,
         }
     }
 
@@ -354,11 +500,39 @@ impl CrashGame {
         let public_seed_hex = hex::encode(public_seed);
         let private_seed_hex = hex::encode(private_seed);
 
+        detach(
+            "journal_round",
+            self.repos.rounds.journal_round(JournalRound {
+                game: GAME_CRASH.to_string(),
+                round_id: round_id.clone(),
+                status: STATUS_RUNNING.to_string(),
+            }),
+        );
+        //* Bets count once the round starts, bets voided by a shutdown never do */
+        for bet in &self.players {
+            detach(
+                "journal_bet",
+                self.repos.rounds.journal_bet(JournalBet {
+                    round_id: round_id.clone(),
+                    user_id: bet.user_id,
+                    amount: bet.bet_amount,
+                }),
+            );
+        }
+        self.round_span = round_span(GAME_CRASH, &round_id);
+        info!(
+            parent: &self.round_span,
+            players = self.players.len(),
+            "Round started"
+        );
         self.round_id = Some(round_id);
         self.public_seed = Some(public_seed_hex.clone());
         self.private_seed = Some(private_seed_hex.clone());
 
-        let crash_point = self.gen_crash_point(&private_seed_hex, &private_seed_hex);
+        let crash_point = match &mut self.crash_points {
+            Some(crash_points) => crash_points(),
+            None => self.gen_crash_point(&private_seed_hex, &private_seed_hex),
+        };
         self.crash_point = Some(crash_point);
         self.interval_active = true;
         self.started_at = Some(Instant::now());
@@ -367,10 +541,28 @@ impl CrashGame {
             msg: format!("Crash Started, public seed: {}", public_seed_hex),
         };
         self.server_addr.do_send(ClientMessage::Json(json));
-        ctx.run_interval(Duration::from_millis(200), |act, ctx| {
+        let tick = self.settings.tick();
+        let mut last_tick = Instant::now();
+        if let Some(ticker) = self.ticker.take() {
+            ctx.cancel_future(ticker);
+        }
+        let ticker = ctx.run_interval(tick, move |act, ctx| {
+            let now = Instant::now();
+            let lag = now.duration_since(last_tick).saturating_sub(tick);
+            metrics().crash_tick_lag.observe(lag.as_secs_f64());
+            last_tick = now;
             if act.interval_active {
                 act.update_game(ctx);
                 if act.crashed {
+                    if let Some(ticker) = act.ticker.take() {
+                        ctx.cancel_future(ticker);
+                    }
+                    info!(
+                        parent: &act.round_span,
+                        crash_point = act.multiplier,
+                        "Round crashed"
+                    );
+                    metrics().record_round(GAME_CRASH);
                     let addr = ctx.address();
                     let json = ClientMessageJson {
                         msg_type: "crash".to_string(),
@@ -386,12 +578,14 @@ impl CrashGame {
                 }
             }
         });
+        self.ticker = Some(ticker);
     }
     fn reset_game(&mut self) {
         self.crashed = false;
         self.crash_point = None;
         self.started_at = None;
         self.round_id = None;
+        self.round_span = Span::none();
         self.public_seed = None;
         self.private_seed = None;
         self.interval_active = false;
@@ -403,7 +597,7 @@ impl CrashGame {
         let mut rng = OsRng;
         let h: u32 = rng.gen();
 
-        if h % 33 == 0 {
+        if h.is_multiple_of(33) {
             return 1.0;
         }
 
@@ -415,7 +609,6 @@ impl CrashGame {
 
         let result_num = u32::from_be_bytes([result[0], result[1], result[2], result[3]]);
         1.00 + (((40.0 * (e as f64 - result_num as f64)) / (result_num as f64)).floor() / 100.0)
-            as f64
     }
 
     fn update_game(&mut self, _ctx: &mut Context<Self>) {
@@ -438,10 +631,7 @@ impl CrashGame {
             } else {
                 self.multiplier = potential_multiplier;
             }
-            println!(
-                "multiplier: {}, crash: {:?}",
-                self.multiplier, self.crash_point
-            );
+            trace!(parent: &self.round_span, multiplier = self.multiplier, "Tick");
             if self.crashed {
                 self.interval_active = false;
                 self.crashed = true;
@@ -1,20 +1,30 @@
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use actix::StreamHandler;
 use actix::{Actor, Addr, Handler};
 use actix_web_actors::ws;
 use serde::Deserialize;
+use tracing::{error, info, warn, Span};
 
+use crate::config::HeartbeatSettings;
+use crate::events::GAME_CRASH;
 use crate::handlers::websocket::crash::crash_server::CashOut;
+use crate::metrics::metrics;
+use crate::rate_limit::{ConnectionLimiter, RATE_LIMITED_MESSAGE};
 
-use super::crash_server::{ClientMessage, Connect, CrashServer, DepositInCrash, Disconnect};
+use super::crash_server::{
+    ClientMessage, ClientMessageJson, Connect, CrashServer, DepositInCrash, Disconnect,
+};
 use actix::ActorContext;
 use actix::AsyncContext;
 
 pub struct CrashWs {
     pub user_id: i32,
     pub hb: Instant,
+    pub heartbeat: HeartbeatSettings,
     pub addr: Addr<CrashServer>,
+    pub limiter: ConnectionLimiter,
+    pub span: Span,
 }
 
 #[derive(Deserialize, Debug)]
@@ -35,24 +45,33 @@ impl Actor for CrashWs {
             user_id: self.user_id,
             addr: ctx.address().recipient(),
         });
-        println!("user with id: {} Connected", self.user_id);
+        info!(parent: &self.span, "Connected");
+        metrics()
+            .ws_connections
+            .with_label_values(&[GAME_CRASH])
+            .inc();
     }
     fn stopped(&mut self, _ctx: &mut Self::Context) {
         self.addr.do_send(Disconnect {
             user_id: self.user_id,
         });
-        println!("user with id: {} Disconnected", self.user_id);
+        info!(parent: &self.span, "Disconnected");
+        metrics()
+            .ws_connections
+            .with_label_values(&[GAME_CRASH])
+            .dec();
     }
 }
 
 impl CrashWs {
     fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        if Instant::now().duration_since(self.hb) >= Duration::from_secs(10) {
-            ctx.stop();
-            return;
-        } else {
-            ctx.ping(b"");
-        }
+        ctx.run_interval(self.heartbeat.interval(), |act, ctx| {
+            if Instant::now().duration_since(act.hb) >= act.heartbeat.timeout() {
+                ctx.stop();
+            } else {
+                ctx.ping(b"");
+            }
+        });
     }
 }
 
@@ -67,8 +86,23 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for CrashWs {
                 self.hb = Instant::now();
             }
             Ok(ws::Message::Text(msg)) => {
-                if let Ok(deserialized_msg) = serde_json::from_str::<IncomingMessage>(&msg) {
-                    println!("{:?}", deserialized_msg);
+                let parsed = serde_json::from_str::<IncomingMessage>(&msg);
+                let action = parsed
+                    .as_ref()
+                    .map(|incoming| incoming.action.as_str())
+                    .unwrap_or("invalid")
+                    .to_string();
+                let rate_limited = || ClientMessageJson {
+                    msg_type: "rate_limited".to_string(),
+                    msg: RATE_LIMITED_MESSAGE.to_string(),
+                };
+                if !self
+                    .limiter
+                    .throttle(Some(self.user_id), &action, rate_limited, ctx)
+                {
+                    return;
+                }
+                if let Ok(deserialized_msg) = parsed {
                     match deserialized_msg.action.as_str() {
                         "deposit" => {
                             if let Ok(amount) =
@@ -86,15 +120,15 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for CrashWs {
                             });
                         }
                         _ => {
-                            println!("Unknown message action: {}", deserialized_msg.action);
+                            warn!(parent: &self.span, "Unknown message action");
                         }
                     }
                 } else {
-                    println!("Failed to deserialize");
+                    warn!(parent: &self.span, "Invalid payload");
                 }
             }
             Err(e) => {
-                eprintln!("Error during WebSocket message: {}", e);
+                warn!(parent: &self.span, error = %e, "Websocket error");
                 ctx.stop();
             }
             _ => (),
@@ -116,7 +150,7 @@ impl Handler<ClientMessage> for CrashWs {
                         ctx.text(json_str);
                     }
                     Err(e) => {
-                        eprintln!("Error converting JSON: {}", e);
+                        error!(parent: &self.span, error = %e, "Failed to serialize message");
                     }
                 }
             }
@@ -10,19 +10,32 @@ use crash_server::CrashServer;
 use crash_ws::CrashWs;
 use rand::Rng;
 
+use crate::{
+    config::Settings,
+    events::GAME_CRASH,
+    rate_limit::{ConnectionLimiter, RateLimiters},
+    telemetry::connection_span,
+};
+
 pub mod crash_server;
 pub mod crash_ws;
 
 pub async fn handle_crash_ws(
     req: HttpRequest,
     serv: Data<Addr<CrashServer>>,
+    rate_limiters: Data<RateLimiters>,
+    settings: Data<Settings>,
     stream: Payload,
 ) -> impl Responder {
+    let user_id = rand::thread_rng().gen_range(1..5000);
     let res = ws::start(
         CrashWs {
-            user_id: rand::thread_rng().gen_range(1..5000),
+            user_id,
             hb: Instant::now(),
+            heartbeat: settings.heartbeat,
             addr: serv.get_ref().clone(),
+            limiter: ConnectionLimiter::new(rate_limiters.crash.clone()),
+            span: connection_span(GAME_CRASH, user_id),
         },
         &req,
         stream,
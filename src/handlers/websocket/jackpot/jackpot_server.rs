@@ -1,69 +1,277 @@
-use actix::{Actor, Addr, AsyncContext, Context, Handler, Message, Recipient};
+use actix::{
+    fut, Actor, ActorFutureExt, Addr, AsyncContext, Context, Handler, Message, MessageResult,
+    Recipient, ResponseActFuture, SpawnHandle, WrapFuture,
+};
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{join_all, LocalBoxFuture},
+    FutureExt,
+};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{collections::HashMap, time::Duration};
+use thiserror::Error;
+use tracing::{debug, error, info, Span};
+use uuid::Uuid;
 
-use crate::db_utils::DbActor;
+use crate::{
+    config::{JackpotSettings, RoomSettings},
+    errors::wallet::WalletError,
+    events::{EventBus, GameResult, GAME_JACKPOT},
+    messages::health::Ping,
+    metrics::metrics,
+    models::{
+        jackpot::NewJackpotRound,
+        round::{STATUS_OPEN, STATUS_REFUNDED, STATUS_RUNNING, STATUS_SETTLED, STATUS_VOIDED},
+        wallet::{
+            REASON_JACKPOT_BET, REASON_JACKPOT_PAYOUT, REASON_JACKPOT_RAKE, REASON_JACKPOT_REFUND,
+        },
+    },
+    repositories::{
+        detach,
+        ledger::ApplyLedgerEntry,
+        rounds::{log_recovery, JournalBet, JournalRound},
+        Repositories,
+    },
+    shutdown::{Shutdown, SHUTDOWN_NOTICE},
+    telemetry::round_span,
+};
+
+//* Wallet and round writes of a settled round, awaited on shutdown so they land before exit */
+type Settlement = Vec<LocalBoxFuture<'static, ()>>;
 
 pub struct JackpotServer {
-    pub sessions: HashMap<i32, Recipient<ClientMessage>>,
+    pub rooms: HashMap<String, JackpotRoom>,
+    //* Room each connected user is currently watching, deposits go to that room */
+    pub members: HashMap<u64, String>,
+    pub settings: JackpotSettings,
+    pub repos: Repositories,
+    pub events: Addr<EventBus>,
+    house_account_id: Option<i32>,
+    shutting_down: bool,
+}
+
+pub struct JackpotRoom {
+    pub config: RoomSettings,
+    pub sessions: HashMap<u64, Recipient<ClientMessage>>,
     pub game_session: Option<GameSession>,
+    countdown: Option<SpawnHandle>,
 }
 
-impl JackpotServer {
-    pub fn new() -> Self {
-        JackpotServer {
+impl JackpotRoom {
+    fn new(config: RoomSettings) -> Self {
+        JackpotRoom {
+            config,
             sessions: HashMap::new(),
             game_session: None,
+            countdown: None,
+        }
+    }
+
+    fn broadcast(&self, msg: ClientMessage) {
+        for addr in self.sessions.values() {
+            addr.do_send(msg.clone());
         }
     }
 
-    fn notify_timer_start(&self, _ctx: &mut Context<Self>) {
-        let timer_start_message = ClientMessage {
-            msg: "The game timer has started. 15 seconds until the winner is chosen.".into(),
-            variant: "timer_start".into(),
+    fn snapshot(&self) -> ClientMessage {
+        let payload = match &self.game_session {
+            Some(session) => json!({
+                "room": self.config.name,
+                "round_id": session.round_id,
+                "phase": session.phase,
+                "pot_total": session.total(),
+                "players": session.standings(),
+                "ends_at": session.ends_at,
+                "seconds_remaining": session.seconds_remaining(),
+            }),
+            None => json!({
+                "room": self.config.name,
+                "round_id": null,
+                "phase": RoundPhase::Waiting,
+                "pot_total": 0.0,
+                "players": [],
+                "ends_at": null,
+                "seconds_remaining": null,
+            }),
         };
+        ClientMessage {
+            msg: "Current round".into(),
+            variant: "snapshot".into(),
+            payload: Some(payload),
+        }
+    }
 
-        for addr in self.sessions.values() {
-            addr.do_send(timer_start_message.clone());
+    fn summary(&self) -> RoomSummary {
+        RoomSummary {
+            name: self.config.name.clone(),
+            min_deposit: self.config.min_deposit,
+            max_deposit: self.config.max_deposit,
+            phase: self
+                .game_session
+                .as_ref()
+                .map(|session| session.phase)
+                .unwrap_or(RoundPhase::Waiting),
+            pot_total: self
+                .game_session
+                .as_ref()
+                .map(|session| session.total())
+                .unwrap_or(0.0),
+            player_count: self
+                .game_session
+                .as_ref()
+                .map(|session| session.players.len())
+                .unwrap_or(0),
+            spectators: self.sessions.len(),
+            seconds_remaining: self
+                .game_session
+                .as_ref()
+                .and_then(|session| session.seconds_remaining()),
+        }
+    }
+
+    fn notify_timer_start(&self, countdown: Duration) {
+        if let Some(session) = &self.game_session {
+            self.broadcast(ClientMessage {
+                msg: format!(
+                    "The game timer has started. {} seconds until the winner is chosen.",
+                    countdown.as_secs()
+                ),
+                variant: "timer_start".into(),
+                payload: Some(json!({
+                    "room": self.config.name,
+                    "round_id": session.round_id,
+                    "phase": session.phase,
+                    "ends_at": session.ends_at,
+                    "seconds_remaining": session.seconds_remaining(),
+                })),
+            });
+        }
+    }
+
+    fn notify_phase(&self) {
+        if let Some(session) = &self.game_session {
+            self.broadcast(ClientMessage {
+                msg: format!("Round is now {:?}", session.phase),
+                variant: "phase".into(),
+                payload: Some(json!({
+                    "room": self.config.name,
+                    "round_id": session.round_id,
+                    "phase": session.phase,
+                })),
+            });
+        }
+    }
+
+    fn notify_countdown(&self) {
+        if let Some(session) = &self.game_session {
+            let remaining = session.seconds_remaining().unwrap_or(0);
+            self.broadcast(ClientMessage {
+                msg: format!("{} seconds remaining", remaining),
+                variant: "countdown".into(),
+                payload: Some(json!({
+                    "room": self.config.name,
+                    "round_id": session.round_id,
+                    "ends_at": session.ends_at,
+                    "seconds_remaining": remaining,
+                })),
+            });
+        }
+    }
+
+    fn extend_countdown(&mut self, settings: &JackpotSettings) {
+        let Some(session) = self.game_session.as_mut() else {
+            return;
+        };
+        let Some(ends_at) = session.ends_at else {
+            return;
+        };
+        let window = settings.late_deposit_window();
+        if window.is_zero() || session.extensions >= settings.max_extensions {
+            return;
+        }
+        let now = Utc::now();
+        if now + window <= ends_at {
+            return;
         }
+
+        session.ends_at = Some(ends_at.max(now + settings.late_deposit_extension()));
+        session.extensions += 1;
+        let msg = ClientMessage {
+            msg: "Late deposit, the countdown has been extended".into(),
+            variant: "timer_extended".into(),
+            payload: Some(json!({
+                "room": self.config.name,
+                "round_id": session.round_id,
+                "ends_at": session.ends_at,
+                "seconds_remaining": session.seconds_remaining(),
+            })),
+        };
+        self.broadcast(msg);
     }
 
-    pub fn notify_winner(&self, player: Player) {
-        let full_amount = self
+    pub fn notify_winner(&self, result: &RoundResult) {
+        let players = self
             .game_session
             .as_ref()
-            .map(|session| {
-                session
-                    .players
-                    .values()
-                    .map(|player| player.deposit)
-                    .sum::<f64>()
-            })
+            .map(|session| session.standings())
+            .unwrap_or_default();
+        let chance = players
+            .iter()
+            .find(|standing| standing.user_id == result.winner.user_id)
+            .map(|standing| standing.chance)
             .unwrap_or(0.0);
 
         let winner_message = ClientMessage {
-            msg: format!("{} has won the jackpot of {}", player.name, full_amount),
+            msg: format!(
+                "{} has won the jackpot of {}",
+                result.winner.name, result.payout
+            ),
             variant: "winner".into(),
+            payload: Some(json!({
+                "room": self.config.name,
+                "round_id": result.round_id,
+                "winner": {
+                    "user_id": result.winner.user_id,
+                    "name": result.winner.name,
+                    "deposit": result.winner.deposit,
+                    "chance": chance,
+                },
+                "winning_ticket": result.winning_ticket,
+                "total_tickets": result.total_tickets,
+                "pot_total": result.pot,
+                "house_fee": result.house_fee,
+                "payout": result.payout,
+                "players": players,
+            })),
         };
 
-        for addr in self.sessions.values() {
-            addr.do_send(winner_message.clone());
-        }
+        self.broadcast(winner_message);
     }
 
-    pub fn notify_player_join(&self, player: &Player) {
+    pub fn notify_player_join(&self, player: &Player, amount: f64) {
+        let Some(session) = &self.game_session else {
+            return;
+        };
         let player_join_message = ClientMessage {
             msg: format!(
                 "{} has joined the game with a deposit of {}",
-                player.name, player.deposit
+                player.name, amount
             ),
             variant: "player_join".into(),
+            payload: Some(json!({
+                "room": self.config.name,
+                "round_id": session.round_id,
+                "user_id": player.user_id,
+                "name": player.name,
+                "amount": amount,
+                "pot_total": session.total(),
+                "players": session.standings(),
+            })),
         };
 
-        for addr in self.sessions.values() {
-            addr.do_send(player_join_message.clone());
-        }
+        self.broadcast(player_join_message);
     }
 
     pub fn reset_game(&mut self) {
@@ -71,87 +279,686 @@ impl JackpotServer {
         let game_reset_message = ClientMessage {
             msg: "Resetting game!".into(),
             variant: "reset".into(),
+            payload: Some(json!({ "room": self.config.name })),
         };
 
-        for addr in self.sessions.values() {
-            addr.do_send(game_reset_message.clone());
+        self.broadcast(game_reset_message);
+    }
+}
+
+impl JackpotServer {
+    //* Without a house account the pot is paid out in full */
+    pub fn new(
+        repos: Repositories,
+        settings: JackpotSettings,
+        events: Addr<EventBus>,
+        house_account_id: Option<i32>,
+    ) -> Self {
+        let rooms = settings
+            .rooms
+            .iter()
+            .map(|room| (room.name.clone(), JackpotRoom::new(room.clone())))
+            .collect();
+        JackpotServer {
+            rooms,
+            members: HashMap::new(),
+            settings,
+            repos,
+            events,
+            house_account_id,
+            shutting_down: false,
+        }
+    }
+
+    fn default_room(&self) -> Option<&str> {
+        self.settings.rooms.first().map(|room| room.name.as_str())
+    }
+
+    //* Sockets that asked for no room, or one that doesn't exist, are in the default room */
+    fn resolve_room(&self, room: Option<String>) -> Option<String> {
+        room.filter(|room| self.rooms.contains_key(room))
+            .or(self.default_room().map(str::to_string))
+    }
+
+    fn start_countdown(&mut self, room_name: &str, ctx: &mut Context<Self>) {
+        let Some(room) = self.rooms.get_mut(room_name) else {
+            return;
+        };
+        let Some(session) = room.game_session.as_mut() else {
+            return;
+        };
+        session.phase = RoundPhase::Countdown;
+        session.ends_at = Some(Utc::now() + self.settings.countdown());
+        info!(
+            parent: &session.span,
+            players = session.players.len(),
+            "Countdown started"
+        );
+        detach(
+            "journal_round",
+            self.repos.rounds.journal_round(JournalRound {
+                game: GAME_JACKPOT.to_string(),
+                round_id: session.round_id.clone(),
+                status: STATUS_RUNNING.to_string(),
+            }),
+        );
+        room.notify_timer_start(self.settings.countdown());
+
+        let room_name = room_name.to_string();
+        let handle = ctx.run_interval(Duration::from_secs(1), move |act, ctx| {
+            let Some(room) = act.rooms.get_mut(&room_name) else {
+                return;
+            };
+            let remaining = room
+                .game_session
+                .as_ref()
+                .and_then(|session| session.seconds_remaining());
+            match remaining {
+                Some(remaining) if remaining > 0 => room.notify_countdown(),
+                _ => {
+                    if let Some(handle) = room.countdown.take() {
+                        ctx.cancel_future(handle);
+                    }
+                    let settlement = act.draw_winner(&room_name);
+                    ctx.spawn(join_all(settlement).into_actor(act).map(|_, _, _| ()));
+                }
+            }
+        });
+        room.countdown = Some(handle);
+    }
+
+    fn draw_winner(&mut self, room_name: &str) -> Settlement {
+        let Some(room) = self.rooms.get_mut(room_name) else {
+            return Vec::new();
+        };
+        let Some(session) = room.game_session.as_mut() else {
+            return Vec::new();
+        };
+        session.phase = RoundPhase::Drawing;
+        let span = session.span.clone();
+        room.notify_phase();
+
+        let result = room.game_session.as_ref().and_then(|session| {
+            let draw = session.start_game()?;
+            let pot = session.total();
+            //* Without a house account to credit, the rake is waived rather than lost */
+            let house_fee = match self.house_account_id {
+                Some(_) => self.settings.house_fee(pot),
+                None => 0.0,
+            };
+            Some(RoundResult {
+                round_id: session.round_id.clone(),
+                winner: draw.winner,
+                winning_ticket: draw.winning_ticket,
+                total_tickets: draw.total_tickets,
+                player_count: session.players.len(),
+                pot,
+                house_fee,
+                payout: pot - house_fee,
+            })
+        });
+        if let Some(result) = result {
+            info!(
+                parent: &span,
+                winner_id = result.winner.user_id,
+                pot = result.pot,
+                payout = result.payout,
+                house_fee = result.house_fee,
+                "Round settled"
+            );
+            metrics().record_round(GAME_JACKPOT);
+            metrics().record_payout(GAME_JACKPOT, result.payout);
+            metrics().record_house_profit(GAME_JACKPOT, result.house_fee);
+            if let Some(session) = room.game_session.as_mut() {
+                session.phase = RoundPhase::Finished;
+            }
+            room.notify_phase();
+            room.notify_winner(&result);
+            self.events.do_send(GameResult {
+                game: GAME_JACKPOT.to_string(),
+                round_id: result.round_id.clone(),
+                user_id: result.winner.user_id,
+                username: result.winner.name.clone(),
+                stake: result.winner.deposit,
+                payout: result.payout,
+            });
+            room.reset_game();
+            return vec![self.settle_round(result, span)];
         }
+        Vec::new()
+    }
+
+    //* Refunds every deposit of a round that never started, e.g. a lone player at shutdown */
+    fn refund_round(&mut self, room_name: &str) -> Settlement {
+        let Some(room) = self.rooms.get_mut(room_name) else {
+            return Vec::new();
+        };
+        let Some(session) = room.game_session.take() else {
+            return Vec::new();
+        };
+        info!(
+            parent: &session.span,
+            players = session.players.len(),
+            pot = session.total(),
+            "Round refunded"
+        );
+        room.broadcast(ClientMessage {
+            msg: "The round was cancelled and deposits refunded".into(),
+            variant: "round_cancelled".into(),
+            payload: Some(json!({
+                "room": room.config.name,
+                "round_id": session.round_id,
+            })),
+        });
+        let repos = self.repos.clone();
+        let deposits: Vec<(i32, f64)> = session
+            .players
+            .values()
+            .map(|player| (player.user_id, player.deposit))
+            .collect();
+        let refund = async move {
+            let round_id = session.round_id.clone();
+            let mut refunded = true;
+            for (user_id, amount) in deposits {
+                let refund = repos.ledger.apply(ApplyLedgerEntry {
+                    user_id,
+                    amount,
+                    reason: REASON_JACKPOT_REFUND.to_string(),
+                    reference: Some(round_id.clone()),
+                });
+                if let Err(e) = refund.await {
+                    error!(
+                        parent: &session.span,
+                        round_id = %round_id,
+                        user_id,
+                        amount,
+                        error = %e,
+                        "Failed to refund deposit"
+                    );
+                    refunded = false;
+                }
+            }
+            //* Left open otherwise, so startup recovery refunds what is still staked */
+            if refunded {
+                journal(&repos, &round_id, STATUS_REFUNDED);
+            }
+        };
+        vec![refund.boxed_local()]
     }
 }
 
+fn journal(repos: &Repositories, round_id: &str, status: &str) {
+    detach(
+        "journal_round",
+        repos.rounds.journal_round(JournalRound {
+            game: GAME_JACKPOT.to_string(),
+            round_id: round_id.to_string(),
+            status: status.to_string(),
+        }),
+    );
+}
+
 impl Actor for JackpotServer {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let recover = self
+            .repos
+            .rounds
+            .recover(GAME_JACKPOT.to_string(), Utc::now().naive_utc());
+        ctx.spawn(
+            recover
+                .into_actor(self)
+                .map(|result, _act, _ctx| log_recovery(GAME_JACKPOT, result)),
+        );
+    }
 }
 
 #[derive(Message)]
-#[rtype(result = "()")]
+#[rtype(result = "Result<(),DepositError>")]
 pub struct Deposit {
-    pub player: Player,
-    pub db_pool: Addr<DbActor>,
+    //* The deposit goes to the room this connection is in */
+    pub conn_id: u64,
+    pub user_id: i32,
+    pub name: String,
+    pub amount: f64,
 }
 
-impl Handler<Deposit> for JackpotServer {
-    type Result = ();
+#[derive(Error, Debug)]
+pub enum DepositError {
+    #[error("Deposit must be at least {0}")]
+    BelowMinimum(f64),
+    #[error("Deposit must be at most {0}")]
+    AboveMaximum(f64),
+    #[error("Deposits are limited to {0} per player in a round")]
+    RoundLimitReached(f64),
+    #[error("This round has reached its player limit")]
+    RoundFull,
+    #[error("The round was drawn before the deposit went through")]
+    RoundClosed,
+    #[error("Room not found")]
+    RoomNotFound,
+    #[error("Join a room before depositing")]
+    NotInRoom,
+    #[error("Insufficient funds")]
+    InsufficientFunds,
+    #[error("The server is shutting down")]
+    ShuttingDown,
+    #[error("Internal error")]
+    InternalError,
+}
 
-    fn handle(&mut self, msg: Deposit, ctx: &mut Self::Context) -> Self::Result {
-        if let Some(ref mut session) = self.game_session {
-            session.add_player(msg.player.clone());
-        } else {
-            let mut new_session = GameSession::new();
-            new_session.add_player(msg.player.clone());
-            self.game_session = Some(new_session);
+impl JackpotServer {
+    //* A round opened for a deposit that didn't go through is dropped, not left open with nobody in it */
+    fn discard_if_empty(&mut self, room_name: &str, round_id: &str) {
+        let Some(room) = self.rooms.get_mut(room_name) else {
+            return;
+        };
+        let empty = room
+            .game_session
+            .as_ref()
+            .is_some_and(|session| session.round_id == round_id && session.players.is_empty());
+        if empty {
+            room.game_session = None;
+            journal(&self.repos, round_id, STATUS_VOIDED);
         }
+    }
 
-        self.notify_player_join(&msg.player);
+    fn check_deposit(
+        &self,
+        room_name: &str,
+        user_id: i32,
+        amount: f64,
+    ) -> Result<(), DepositError> {
+        if self.shutting_down {
+            return Err(DepositError::ShuttingDown);
+        }
+        let room = self
+            .rooms
+            .get(room_name)
+            .ok_or(DepositError::RoomNotFound)?;
+        if amount.is_nan() || amount < room.config.min_deposit {
+            return Err(DepositError::BelowMinimum(room.config.min_deposit));
+        }
+        if amount > room.config.max_deposit {
+            return Err(DepositError::AboveMaximum(room.config.max_deposit));
+        }
+        if let Some(session) = &room.game_session {
+            if matches!(session.phase, RoundPhase::Drawing | RoundPhase::Finished) {
+                return Err(DepositError::RoundClosed);
+            }
+            let staked = session.players.get(&user_id).map(|p| p.deposit);
+            if staked.is_none() && session.players.len() >= self.settings.max_players {
+                return Err(DepositError::RoundFull);
+            }
+            if staked.unwrap_or(0.0) + amount > self.settings.max_round_deposit {
+                return Err(DepositError::RoundLimitReached(
+                    self.settings.max_round_deposit,
+                ));
+            }
+        }
+        Ok(())
+    }
 
-        msg.db_pool.send(RecordDeposit {
-            user_id: msg.player.user_id,
-            amount: msg.player.deposit,
-        });
+    fn accept_deposit(
+        &mut self,
+        room_name: &str,
+        msg: &Deposit,
+        round_id: &str,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), DepositError> {
+        //* Limits are checked again since other deposits may have landed while the stake was debited */
+        self.check_deposit(room_name, msg.user_id, msg.amount)?;
 
-        if let Some(ref mut session) = self.game_session {
-            if session.players.len() >= 2 && !session.timer_started {
-                session.timer_started = true;
-                self.notify_timer_start(ctx);
-                ctx.run_later(Duration::from_secs(15), |act, _ctx| {
-                    if let Some(ref mut session) = act.game_session {
-                        if let Some(winner) = session.start_game() {
-                            act.notify_winner(winner);
-                            act.reset_game();
-                        }
-                    }
+        let room = self
+            .rooms
+            .get_mut(room_name)
+            .ok_or(DepositError::RoomNotFound)?;
+        let Some(session) = room
+            .game_session
+            .as_mut()
+            .filter(|session| session.round_id == round_id)
+        else {
+            return Err(DepositError::RoundClosed);
+        };
+        let player = session
+            .add_deposit(msg.user_id, &msg.name, msg.amount)
+            .clone();
+        debug!(
+            parent: &session.span,
+            user_id = msg.user_id,
+            amount = msg.amount,
+            "Deposit accepted"
+        );
+        metrics().record_bet(GAME_JACKPOT, msg.amount);
+        detach(
+            "journal_bet",
+            self.repos.rounds.journal_bet(JournalBet {
+                round_id: round_id.to_string(),
+                user_id: msg.user_id,
+                amount: msg.amount,
+            }),
+        );
+        let (phase, player_count) = (session.phase, session.players.len());
+        room.notify_player_join(&player, msg.amount);
+
+        match phase {
+            RoundPhase::Waiting if player_count >= 2 => self.start_countdown(room_name, ctx),
+            RoundPhase::Countdown => room.extend_countdown(&self.settings),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    //* The winner is paid before the rake is taken; the round stays open in the journal until every write went through, so startup recovery picks it up */
+    fn settle_round(&self, result: RoundResult, span: Span) -> LocalBoxFuture<'static, ()> {
+        let repos = self.repos.clone();
+        let house_account_id = self.house_account_id;
+        async move {
+            let round_id = result.round_id.clone();
+            let payout = repos.ledger.apply(ApplyLedgerEntry {
+                user_id: result.winner.user_id,
+                amount: result.payout,
+                reason: REASON_JACKPOT_PAYOUT.to_string(),
+                reference: Some(round_id.clone()),
+            });
+            if let Err(e) = payout.await {
+                error!(
+                    parent: &span,
+                    round_id = %round_id,
+                    user_id = result.winner.user_id,
+                    amount = result.payout,
+                    error = %e,
+                    "Failed to pay the winner"
+                );
+                return;
+            }
+
+            let mut settled = true;
+            if let (Some(house_id), true) = (house_account_id, result.house_fee > 0.0) {
+                let rake = repos.ledger.apply(ApplyLedgerEntry {
+                    user_id: house_id,
+                    amount: result.house_fee,
+                    reason: REASON_JACKPOT_RAKE.to_string(),
+                    reference: Some(round_id.clone()),
                 });
+                if let Err(e) = rake.await {
+                    error!(
+                        parent: &span,
+                        round_id = %round_id,
+                        user_id = house_id,
+                        amount = result.house_fee,
+                        error = %e,
+                        "Failed to credit the rake"
+                    );
+                    settled = false;
+                }
+            }
+
+            let record = repos.rounds.record_jackpot(NewJackpotRound {
+                round_id: round_id.clone(),
+                winner_id: result.winner.user_id,
+                winner_name: result.winner.name.clone(),
+                pot: result.pot,
+                house_fee: result.house_fee,
+                payout: result.payout,
+                winning_ticket: result.winning_ticket as i64,
+                total_tickets: result.total_tickets as i64,
+                player_count: result.player_count as i32,
+            });
+            if let Err(e) = record.await {
+                error!(
+                    parent: &span,
+                    round_id = %round_id,
+                    user_id = result.winner.user_id,
+                    amount = result.pot,
+                    error = %e,
+                    "Failed to record the round"
+                );
+                settled = false;
+            }
+
+            if settled {
+                journal(&repos, &round_id, STATUS_SETTLED);
             }
         }
+        .boxed_local()
+    }
+}
+
+impl Handler<Deposit> for JackpotServer {
+    type Result = ResponseActFuture<Self, Result<(), DepositError>>;
+
+    fn handle(&mut self, msg: Deposit, _ctx: &mut Self::Context) -> Self::Result {
+        let Some(room_name) = self.members.get(&msg.conn_id).cloned() else {
+            return Box::pin(fut::ready(Err(DepositError::NotInRoom)));
+        };
+        if let Err(e) = self.check_deposit(&room_name, msg.user_id, msg.amount) {
+            return Box::pin(fut::ready(Err(e)));
+        }
+        let Some(room) = self.rooms.get_mut(&room_name) else {
+            return Box::pin(fut::ready(Err(DepositError::RoomNotFound)));
+        };
+        let opened = room.game_session.is_none();
+        let session = room.game_session.get_or_insert_with(GameSession::new);
+        let round_id = session.round_id.clone();
+        if opened {
+            info!(parent: &session.span, room = %room_name, "Round opened");
+        }
+        let journal = (!session.journaled).then(|| {
+            self.repos.rounds.journal_round(JournalRound {
+                game: GAME_JACKPOT.to_string(),
+                round_id: round_id.clone(),
+                status: STATUS_OPEN.to_string(),
+            })
+        });
+        let span = session.span.clone();
+        let ledger = self.repos.ledger.clone();
+        let entry = ApplyLedgerEntry {
+            user_id: msg.user_id,
+            amount: -msg.amount,
+            reason: REASON_JACKPOT_BET.to_string(),
+            reference: Some(round_id.clone()),
+        };
+
+        //* No stake is taken before the round is in the journal, so a restart always finds it to refund */
+        let debit = async move {
+            if let Some(journal) = journal {
+                if let Err(e) = journal.await {
+                    error!(parent: &span, error = %e, "Failed to journal round");
+                    return Err(DepositError::InternalError);
+                }
+            }
+            ledger.apply(entry).await.map_err(|e| match e {
+                WalletError::InsufficientFunds => DepositError::InsufficientFunds,
+                _ => DepositError::InternalError,
+            })
+        };
+
+        Box::pin(debit.into_actor(self).map(move |result, act, ctx| {
+            if let Err(e) = result {
+                act.discard_if_empty(&room_name, &round_id);
+                return Err(e);
+            }
+            if let Some(session) = act
+                .rooms
+                .get_mut(&room_name)
+                .and_then(|room| room.game_session.as_mut())
+                .filter(|session| session.round_id == round_id)
+            {
+                session.journaled = true;
+            }
+
+            act.accept_deposit(&room_name, &msg, &round_id, ctx)
+                .inspect_err(|_| {
+                    detach(
+                        "apply_ledger_entry",
+                        act.repos.ledger.apply(ApplyLedgerEntry {
+                            user_id: msg.user_id,
+                            amount: msg.amount,
+                            reason: REASON_JACKPOT_REFUND.to_string(),
+                            reference: Some(round_id.clone()),
+                        }),
+                    );
+                })
+        }))
     }
 }
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct Connect {
-    pub user_id: i32,
+    pub conn_id: u64,
     pub addr: Recipient<ClientMessage>,
+    //* Falls back to the default room when missing or unknown */
+    pub room: Option<String>,
 }
 
 impl Handler<Connect> for JackpotServer {
     type Result = ();
 
     fn handle(&mut self, msg: Connect, _ctx: &mut Self::Context) -> Self::Result {
-        self.sessions.insert(msg.user_id, msg.addr);
+        let room_name = self.resolve_room(msg.room);
+        if let Some(room) = room_name.as_ref().and_then(|name| self.rooms.get_mut(name)) {
+            msg.addr.do_send(room.snapshot());
+            room.sessions.insert(msg.conn_id, msg.addr);
+        }
+        if let Some(room_name) = room_name {
+            self.members.insert(msg.conn_id, room_name);
+        }
     }
 }
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct Disconnect {
-    pub user_id: i32,
+    pub conn_id: u64,
 }
 
 impl Handler<Disconnect> for JackpotServer {
     type Result = ();
 
     fn handle(&mut self, msg: Disconnect, _ctx: &mut Self::Context) -> Self::Result {
-        self.sessions.remove(&msg.user_id);
+        if let Some(room_name) = self.members.remove(&msg.conn_id) {
+            if let Some(room) = self.rooms.get_mut(&room_name) {
+                room.sessions.remove(&msg.conn_id);
+            }
+        }
+    }
+}
+
+impl Handler<Shutdown> for JackpotServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: Shutdown, ctx: &mut Self::Context) -> Self::Result {
+        self.shutting_down = true;
+        let room_names: Vec<String> = self.rooms.keys().cloned().collect();
+        let mut settlement = Vec::new();
+        for room_name in room_names {
+            let Some(room) = self.rooms.get_mut(&room_name) else {
+                continue;
+            };
+            if let Some(handle) = room.countdown.take() {
+                ctx.cancel_future(handle);
+            }
+            //* A round with its countdown running has enough players to be drawn early */
+            let counting_down = room
+                .game_session
+                .as_ref()
+                .is_some_and(|session| matches!(session.phase, RoundPhase::Countdown));
+            settlement.extend(if counting_down {
+                self.draw_winner(&room_name)
+            } else {
+                self.refund_round(&room_name)
+            });
+            if let Some(room) = self.rooms.get(&room_name) {
+                room.broadcast(ClientMessage {
+                    msg: SHUTDOWN_NOTICE.into(),
+                    variant: "shutdown".into(),
+                    payload: Some(json!({ "room": room.config.name })),
+                });
+            }
+        }
+        Box::pin(join_all(settlement).into_actor(self).map(|_, _, _| ()))
+    }
+}
+
+impl Handler<Ping> for JackpotServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {}
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(),JoinRoomError>")]
+pub struct JoinRoom {
+    pub conn_id: u64,
+    pub room: String,
+}
+
+#[derive(Error, Debug)]
+pub enum JoinRoomError {
+    #[error("Room not found")]
+    RoomNotFound,
+    #[error("Not connected")]
+    NotConnected,
+}
+
+impl Handler<JoinRoom> for JackpotServer {
+    type Result = Result<(), JoinRoomError>;
+
+    fn handle(&mut self, msg: JoinRoom, _ctx: &mut Self::Context) -> Self::Result {
+        if !self.rooms.contains_key(&msg.room) {
+            return Err(JoinRoomError::RoomNotFound);
+        }
+        let current = self
+            .members
+            .get(&msg.conn_id)
+            .cloned()
+            .ok_or(JoinRoomError::NotConnected)?;
+        let addr = self
+            .rooms
+            .get_mut(&current)
+            .and_then(|room| room.sessions.remove(&msg.conn_id))
+            .ok_or(JoinRoomError::NotConnected)?;
+
+        if let Some(room) = self.rooms.get_mut(&msg.room) {
+            addr.do_send(room.snapshot());
+            room.sessions.insert(msg.conn_id, addr);
+        }
+        self.members.insert(msg.conn_id, msg.room);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RoomSummary {
+    pub name: String,
+    pub min_deposit: f64,
+    pub max_deposit: f64,
+    pub phase: RoundPhase,
+    pub pot_total: f64,
+    pub player_count: usize,
+    pub spectators: usize,
+    pub seconds_remaining: Option<i64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Vec<RoomSummary>")]
+pub struct ListRooms;
+
+impl Handler<ListRooms> for JackpotServer {
+    type Result = MessageResult<ListRooms>;
+
+    fn handle(&mut self, _msg: ListRooms, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(
+            self.settings
+                .rooms
+                .iter()
+                .filter_map(|room| self.rooms.get(&room.name))
+                .map(JackpotRoom::summary)
+                .collect(),
+        )
     }
 }
 
@@ -160,14 +967,16 @@ impl Handler<Disconnect> for JackpotServer {
 pub struct ClientMessage {
     pub msg: String,
     pub variant: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
 }
 
 impl Handler<ClientMessage> for JackpotServer {
     type Result = ();
 
     fn handle(&mut self, msg: ClientMessage, _ctx: &mut Self::Context) -> Self::Result {
-        for addr in self.sessions.values() {
-            addr.do_send(msg.clone());
+        for room in self.rooms.values() {
+            room.broadcast(msg.clone());
         }
     }
 }
@@ -176,54 +985,168 @@ impl Handler<ClientMessage> for JackpotServer {
 pub struct Player {
     pub user_id: i32,
     pub name: String,
+    //* Sum of every deposit the player made in the round */
     pub deposit: f64,
+    pub deposits: Vec<PlayerDeposit>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlayerDeposit {
+    pub amount: f64,
+    pub deposited_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundPhase {
+    Waiting,
+    Countdown,
+    Drawing,
+    Finished,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlayerStanding {
+    pub user_id: i32,
+    pub name: String,
+    pub deposit: f64,
+    pub deposits: Vec<PlayerDeposit>,
+    //* Percentage of the pot, which is the player's chance of winning */
+    pub chance: f64,
+    //* Inclusive range of tickets held by the player, one ticket per 0.01 deposited */
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Draw {
+    pub winner: Player,
+    pub winning_ticket: u64,
+    pub total_tickets: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct RoundResult {
+    pub round_id: String,
+    pub winner: Player,
+    pub winning_ticket: u64,
+    pub total_tickets: u64,
+    pub player_count: usize,
+    pub pot: f64,
+    pub house_fee: f64,
+    pub payout: f64,
 }
 
 #[derive(Clone, Debug)]
 pub struct GameSession {
+    pub round_id: String,
     pub players: HashMap<i32, Player>,
-    pub timer_started: bool,
+    pub phase: RoundPhase,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub extensions: u32,
+    //* Set once the OPEN journal write went through, later deposits skip it */
+    pub journaled: bool,
+    pub span: Span,
 }
 
 impl GameSession {
     pub fn new() -> Self {
+        let round_id = Uuid::new_v4().to_string();
         Self {
+            span: round_span(GAME_JACKPOT, &round_id),
+            round_id,
             players: HashMap::new(),
-            timer_started: false,
+            phase: RoundPhase::Waiting,
+            ends_at: None,
+            extensions: 0,
+            journaled: false,
         }
     }
 
-    pub fn add_player(&mut self, player: Player) {
-        self.players.insert(player.user_id, player);
+    pub fn seconds_remaining(&self) -> Option<i64> {
+        self.ends_at
+            .map(|ends_at| (ends_at - Utc::now()).num_seconds().max(0))
     }
 
-    pub fn start_game(&self) -> Option<Player> {
-        let full_amount: f64 = self.players.values().map(|x| x.deposit).sum();
-        let mut rand = rand::thread_rng();
-        let mut roll = rand.gen_range(0.0..full_amount);
+    pub fn standings(&self) -> Vec<PlayerStanding> {
+        let total = self.total();
+        self.ticket_ranges()
+            .into_iter()
+            .map(|(player, ticket_start, ticket_end)| PlayerStanding {
+                user_id: player.user_id,
+                name: player.name.clone(),
+                deposit: player.deposit,
+                deposits: player.deposits.clone(),
+                chance: if total > 0.0 {
+                    player.deposit / total * 100.0
+                } else {
+                    0.0
+                },
+                ticket_start,
+                ticket_end,
+            })
+            .collect()
+    }
 
-        for player in self.players.values() {
-            if roll <= player.deposit {
-                return Some(player.clone());
-            } else {
-                roll -= player.deposit;
-            }
-        }
-        None
+    //* Tickets are handed out in the order players first deposited */
+    fn ticket_ranges(&self) -> Vec<(&Player, u64, u64)> {
+        let mut players: Vec<&Player> = self.players.values().collect();
+        players.sort_by_key(|player| {
+            (
+                player.deposits.first().map(|deposit| deposit.deposited_at),
+                player.user_id,
+            )
+        });
+
+        let mut next_ticket = 1;
+        players
+            .into_iter()
+            .map(|player| {
+                let tickets = ((player.deposit * 100.0).round() as u64).max(1);
+                let range = (player, next_ticket, next_ticket + tickets - 1);
+                next_ticket += tickets;
+                range
+            })
+            .collect()
     }
-}
 
-#[derive(Message)]
-#[rtype(result = "()")]
-pub struct RecordDeposit {
-    pub user_id: i32,
-    pub amount: f64,
-}
+    pub fn add_deposit(&mut self, user_id: i32, name: &str, amount: f64) -> &Player {
+        let player = self.players.entry(user_id).or_insert_with(|| Player {
+            user_id,
+            name: name.to_string(),
+            deposit: 0.0,
+            deposits: Vec::new(),
+        });
+        player.deposits.push(PlayerDeposit {
+            amount,
+            deposited_at: Utc::now(),
+        });
+        player.deposit += amount;
+        player
+    }
 
-impl Handler<RecordDeposit> for DbActor {
-    type Result = ();
+    pub fn total(&self) -> f64 {
+        self.players.values().map(|player| player.deposit).sum()
+    }
+
+    pub fn start_game(&self) -> Option<Draw> {
+        let ranges = self.ticket_ranges();
+        let total_tickets = ranges.last().map(|(_, _, end)| *end)?;
+        let winning_ticket = rand::thread_rng().gen_range(1..=total_tickets);
+
+        ranges
+            .into_iter()
+            .find(|(_, start, end)| (*start..=*end).contains(&winning_ticket))
+            .map(|(player, _, _)| Draw {
+                winner: player.clone(),
+                winning_ticket,
+                total_tickets,
+            })
+    }
+}
 
-    fn handle(&mut self, msg: RecordDeposit, ctx: &mut Self::Context) -> Self::Result {
-        let conn = self.0.get().expect("Failed to connect");
+impl Default for GameSession {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -1,55 +1,175 @@
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-use actix::{Actor, Addr, Handler, StreamHandler};
+use actix::{Actor, ActorContext, ActorFutureExt, Addr, Handler, StreamHandler, WrapFuture};
 use actix_web_actors::ws;
 use serde::Deserialize;
+use tracing::{error, info, warn, Span};
 
-use crate::db_utils::DbActor;
-
-use super::jackpot_server::{ClientMessage, Connect, Deposit, Disconnect, JackpotServer, Player};
+use super::jackpot_server::{ClientMessage, Connect, Deposit, Disconnect, JackpotServer, JoinRoom};
+use crate::{
+    config::HeartbeatSettings,
+    events::GAME_JACKPOT,
+    handlers::websocket::LOGIN_REQUIRED,
+    metrics::metrics,
+    rate_limit::{ConnectionLimiter, RATE_LIMITED_MESSAGE},
+};
 use actix::AsyncContext;
 
 pub struct JackpotWs {
     pub addr: Addr<JackpotServer>,
     pub hb: Instant,
+    pub heartbeat: HeartbeatSettings,
+    pub conn_id: u64,
     pub user_id: i32,
     pub name: Option<String>,
-    pub db_pool: Option<Addr<DbActor>>,
+    //* Room asked for when connecting, the server keeps track of it from there */
+    pub room: Option<String>,
+    pub limiter: ConnectionLimiter,
+    pub span: Span,
 }
 #[derive(Deserialize, Debug)]
 pub struct DepositPayload {
     pub amount: f64,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum JackpotCommand {
+    Deposit { amount: f64 },
+    JoinRoom { room: String },
+}
+
+impl JackpotCommand {
+    //* A bare {"amount": ..} payload is still accepted as a deposit */
+    fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str::<JackpotCommand>(text)
+            .ok()
+            .or_else(|| {
+                serde_json::from_str::<DepositPayload>(text)
+                    .ok()
+                    .map(|payload| JackpotCommand::Deposit {
+                        amount: payload.amount,
+                    })
+            })
+    }
+}
+
 impl Actor for JackpotWs {
     type Context = ws::WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        println!(
-            "User with id {} started, Authorized: {}",
-            self.user_id,
-            self.name.is_some()
+        info!(
+            parent: &self.span,
+            authorized = self.name.is_some(),
+            "Connected"
         );
+        metrics()
+            .ws_connections
+            .with_label_values(&[GAME_JACKPOT])
+            .inc();
         self.hb(ctx);
         self.addr.do_send(Connect {
-            user_id: self.user_id.clone(),
+            conn_id: self.conn_id,
             addr: ctx.address().recipient(),
+            room: self.room.clone(),
         })
     }
     fn stopped(&mut self, _ctx: &mut Self::Context) {
+        info!(parent: &self.span, "Disconnected");
+        metrics()
+            .ws_connections
+            .with_label_values(&[GAME_JACKPOT])
+            .dec();
         self.addr.do_send(Disconnect {
-            user_id: self.user_id.clone(),
+            conn_id: self.conn_id,
         })
     }
 }
 
 impl JackpotWs {
     fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        if Instant::now().duration_since(self.hb) > Duration::from_secs(15) {
-            ctx.close(None);
+        ctx.run_interval(self.heartbeat.interval(), |act, ctx| {
+            if Instant::now().duration_since(act.hb) > act.heartbeat.timeout() {
+                ctx.close(None);
+                ctx.stop();
+            } else {
+                ctx.ping(b"");
+            }
+        });
+    }
+
+    fn deposit(&self, amount: f64, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(name) = &self.name else {
+            self.reply(
+                ctx,
+                ClientMessage {
+                    msg: LOGIN_REQUIRED.to_string(),
+                    variant: "deposit_rejected".to_string(),
+                    payload: None,
+                },
+            );
             return;
-        } else {
-            ctx.ping(b"");
+        };
+        let deposit = Deposit {
+            conn_id: self.conn_id,
+            user_id: self.user_id,
+            name: name.to_string(),
+            amount,
+        };
+        ctx.spawn(
+            self.addr
+                .send(deposit)
+                .into_actor(self)
+                .map(|result, act, ctx| {
+                    let reason = match result {
+                        Ok(Ok(())) => return,
+                        Ok(Err(e)) => e.to_string(),
+                        Err(_) => "Something went wrong".to_string(),
+                    };
+                    act.reply(
+                        ctx,
+                        ClientMessage {
+                            msg: reason,
+                            variant: "deposit_rejected".to_string(),
+                            payload: None,
+                        },
+                    );
+                }),
+        );
+    }
+
+    fn join_room(&self, room: String, ctx: &mut ws::WebsocketContext<Self>) {
+        let join = JoinRoom {
+            conn_id: self.conn_id,
+            room,
+        };
+        ctx.spawn(self.addr.send(join).into_actor(self).map(
+            move |result, act, ctx| match result {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => act.reply(
+                    ctx,
+                    ClientMessage {
+                        msg: e.to_string(),
+                        variant: "join_room_rejected".to_string(),
+                        payload: None,
+                    },
+                ),
+                Err(_) => act.reply(
+                    ctx,
+                    ClientMessage {
+                        msg: "Something went wrong".to_string(),
+                        variant: "join_room_rejected".to_string(),
+                        payload: None,
+                    },
+                ),
+            },
+        ));
+    }
+
+    //* Replies to this socket only, unlike server messages which are broadcast */
+    fn reply(&self, ctx: &mut ws::WebsocketContext<Self>, msg: ClientMessage) {
+        if let Ok(json_msg) = serde_json::to_string(&msg) {
+            ctx.text(json_msg);
         }
     }
 }
@@ -65,35 +185,39 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for JackpotWs {
                 self.hb = Instant::now();
             }
             Ok(ws::Message::Text(msg)) => {
-                let deserialized = serde_json::from_str::<DepositPayload>(&msg);
-                if let Some(name) = &self.name {
-                    if let Some(db_pool) = &self.db_pool {
-                        match deserialized {
-                            Ok(msg) => {
-                                let deposit = Deposit {
-                                    player: Player {
-                                        user_id: self.user_id,
-                                        name: name.to_string(),
-                                        deposit: msg.amount,
-                                    },
-                                    db_pool: db_pool.clone(),
-                                };
-                                self.addr.do_send(deposit);
-                            }
-                            Err(_) => {
-                                println!("Closing error: {}", msg);
-                                self.addr.do_send(ClientMessage {
-                                    msg: "Invalid payload".to_string(),
-                                    variant: "error".to_string(),
-                                });
-                                // ctx.close(None);
-                            }
-                        }
+                let command = JackpotCommand::parse(&msg);
+                let name = match &command {
+                    Some(JackpotCommand::Deposit { .. }) => "deposit",
+                    Some(JackpotCommand::JoinRoom { .. }) => "join_room",
+                    None => "invalid",
+                };
+                let user_id = self.name.as_ref().map(|_| self.user_id);
+                let rate_limited = || ClientMessage {
+                    msg: RATE_LIMITED_MESSAGE.to_string(),
+                    variant: "rate_limited".to_string(),
+                    payload: None,
+                };
+                if !self.limiter.throttle(user_id, name, rate_limited, ctx) {
+                    return;
+                }
+                match command {
+                    Some(JackpotCommand::Deposit { amount }) => self.deposit(amount, ctx),
+                    Some(JackpotCommand::JoinRoom { room }) => self.join_room(room, ctx),
+                    None => {
+                        warn!(parent: &self.span, "Invalid payload");
+                        self.reply(
+                            ctx,
+                            ClientMessage {
+                                msg: "Invalid payload".to_string(),
+                                variant: "error".to_string(),
+                                payload: None,
+                            },
+                        );
                     }
                 }
             }
             Err(e) => {
-                eprintln!("Error: {}", e);
+                warn!(parent: &self.span, error = %e, "Websocket error");
                 ctx.close(None);
             }
             _ => (),
@@ -108,8 +232,7 @@ impl Handler<ClientMessage> for JackpotWs {
         if let Ok(json_msg) = serde_json::to_string(&msg) {
             ctx.text(json_msg);
         } else {
-            eprintln!("Failed to serialize");
-            return;
+            error!(parent: &self.span, "Failed to serialize message");
         }
     }
 }
@@ -8,16 +8,24 @@ use actix_web::{
 use actix_web_actors::ws;
 use jackpot_server::JackpotServer;
 use jackpot_ws::JackpotWs;
-use rand::Rng;
+use tracing::{error, warn};
 
-use crate::{db_utils::AppState, jwt::decode_jwt};
+use crate::{
+    config::Settings,
+    events::GAME_JACKPOT,
+    handlers::websocket::{anonymous_id, connection_id},
+    jwt::decode_jwt,
+    rate_limit::{ConnectionLimiter, RateLimiters},
+    telemetry::connection_span,
+};
 
 pub mod jackpot_server;
 pub mod jackpot_ws;
 
 pub async fn handle_jackpot_ws(
     jackpot_serv: Data<Addr<JackpotServer>>,
-    app_state: Data<AppState>,
+    rate_limiters: Data<RateLimiters>,
+    settings: Data<Settings>,
     req: HttpRequest,
     stream: Payload,
 ) -> impl Responder {
@@ -26,17 +34,27 @@ pub async fn handle_jackpot_ws(
         .split('&')
         .find(|&param| param.starts_with("token="))
         .and_then(|param| param.split('=').nth(1));
+    let room = req
+        .query_string()
+        .split('&')
+        .find(|&param| param.starts_with("room="))
+        .and_then(|param| param.split('=').nth(1))
+        .map(str::to_string);
 
     match token {
-        Some(tkn) => match decode_jwt(&tkn) {
+        Some(tkn) => match decode_jwt(tkn) {
             Ok(claims) => {
                 let res = ws::start(
                     JackpotWs {
                         addr: jackpot_serv.get_ref().clone(),
                         name: Some(claims.claims.username),
                         hb: Instant::now(),
+                        heartbeat: settings.heartbeat,
+                        conn_id: connection_id(),
                         user_id: claims.claims.sub,
-                        db_pool: Some(app_state.as_ref().db.clone()),
+                        room,
+                        limiter: ConnectionLimiter::new(rate_limiters.jackpot.clone()),
+                        span: connection_span(GAME_JACKPOT, claims.claims.sub),
                     },
                     &req,
                     stream,
@@ -45,24 +63,29 @@ pub async fn handle_jackpot_ws(
                 match res {
                     Ok(response) => response,
                     Err(e) => {
-                        println!("{:?}", e);
+                        error!(error = %e, "Failed to start websocket");
                         HttpResponse::InternalServerError().finish()
                     }
                 }
             }
             Err(_) => {
-                println!("Couldnot decode jwt");
+                warn!("Rejected websocket with an invalid token");
                 HttpResponse::Unauthorized().finish()
             }
         },
         None => {
+            let user_id = anonymous_id();
             let res = ws::start(
                 JackpotWs {
                     addr: jackpot_serv.get_ref().clone(),
                     name: None,
                     hb: Instant::now(),
-                    user_id: rand::thread_rng().gen_range(1..20000),
-                    db_pool: None,
+                    heartbeat: settings.heartbeat,
+                    conn_id: connection_id(),
+                    user_id,
+                    room,
+                    limiter: ConnectionLimiter::new(rate_limiters.jackpot.clone()),
+                    span: connection_span(GAME_JACKPOT, user_id),
                 },
                 &req,
                 stream,
@@ -1,4 +1,22 @@
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+
 pub mod chat;
 pub mod coinflip;
 pub mod crash;
 pub mod jackpot;
+
+//* Spectators connected without a token can not stake anything */
+pub const LOGIN_REQUIRED: &str = "Log in to play";
+
+static NEXT_ANONYMOUS_ID: AtomicI32 = AtomicI32::new(-1);
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+//* Ids for sockets without a token, negative so they never collide with a user id */
+pub fn anonymous_id() -> i32 {
+    NEXT_ANONYMOUS_ID.fetch_sub(1, Ordering::Relaxed)
+}
+
+//* Tells apart the sockets of one user, each tab can watch a different room */
+pub fn connection_id() -> u64 {
+    NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed)
+}
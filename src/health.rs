@@ -0,0 +1,61 @@
+use std::{sync::Arc, time::Duration};
+
+use actix::Recipient;
+use actix_web::rt;
+use futures::future::join_all;
+use serde::Serialize;
+
+use crate::{messages::health::Ping, repositories::StoreHealth};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    Timeout,
+}
+
+//* Everything /readyz has to hear back from before the process takes traffic */
+pub struct ReadinessProbe {
+    store: Arc<dyn StoreHealth>,
+    servers: Vec<(&'static str, Recipient<Ping>)>,
+    timeout: Duration,
+}
+
+impl ReadinessProbe {
+    pub fn new(store: Arc<dyn StoreHealth>, timeout: Duration) -> Self {
+        ReadinessProbe {
+            store,
+            servers: Vec::new(),
+            timeout,
+        }
+    }
+
+    pub fn add(&mut self, name: &'static str, server: Recipient<Ping>) {
+        self.servers.push((name, server));
+    }
+
+    //* Runs every check at once, each one bounded by the timeout */
+    pub async fn check(&self) -> Vec<(&'static str, CheckStatus)> {
+        let database = async {
+            let status = match rt::time::timeout(self.timeout, self.store.check()).await {
+                Ok(Ok(())) => CheckStatus::Ok,
+                Ok(Err(_)) => CheckStatus::Failed,
+                Err(_) => CheckStatus::Timeout,
+            };
+            ("database", status)
+        };
+        let servers = join_all(self.servers.iter().map(|(name, server)| async move {
+            let status = match rt::time::timeout(self.timeout, server.send(Ping)).await {
+                Ok(Ok(())) => CheckStatus::Ok,
+                Ok(Err(_)) => CheckStatus::Failed,
+                Err(_) => CheckStatus::Timeout,
+            };
+            (*name, status)
+        }));
+
+        let (database, mut checks) = futures::join!(database, servers);
+        checks.insert(0, database);
+        checks
+    }
+}
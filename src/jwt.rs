@@ -5,7 +5,7 @@ use jsonwebtoken::{
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub username: String,
     pub exp: usize,
@@ -31,7 +31,7 @@ pub fn decode_jwt(token: &str) -> Result<TokenData<Claims>, Error> {
     let secret_key = env::var("JWT_SECRET_KEY").expect("secret key not found");
     let decoded = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(&secret_key.as_ref()),
+        &DecodingKey::from_secret(secret_key.as_ref()),
         &Validation::default(),
     );
     decoded
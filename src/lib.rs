@@ -0,0 +1,23 @@
+pub mod accounts;
+pub mod cli;
+pub mod config;
+pub mod db_utils;
+pub mod errors;
+pub mod events;
+pub mod handlers;
+pub mod health;
+pub mod jwt;
+pub mod messages;
+pub mod metrics;
+pub mod middlewares;
+pub mod migrations;
+pub mod models;
+pub mod rate_limit;
+pub mod repositories;
+pub mod routes;
+pub mod schema;
+pub mod server;
+pub mod shutdown;
+pub mod telemetry;
+pub mod validation;
+pub mod websockets;
@@ -1,64 +1,64 @@
-mod actors;
-mod db_utils;
-mod errors;
-mod handlers;
-mod jwt;
-mod messages;
-mod middlewares;
-mod models;
-mod routes;
-mod schema;
-mod validation;
-mod websockets;
-use actix::Actor;
-use actix::SyncArbiter;
-use actix_cors::Cors;
-use actix_web::{http::header, web::Data, App, HttpServer};
-use db_utils::{get_db_pool, AppState, DbActor};
+use clap::Parser;
 use dotenv::dotenv;
-use handlers::websocket::coinflip::coinflip_server::CoinflipServer;
-use handlers::websocket::{
-    chat::chat_server::ChatServer, crash::crash_server::CrashServer,
-    jackpot::jackpot_server::JackpotServer,
+use jackpot_rust::{
+    cli::{self, Cli, Command},
+    config::{Settings, StorageBackend},
+    db_utils::get_db_pool,
+    migrations,
+    repositories::Repositories,
+    server::{self, GameServers},
+    telemetry,
 };
-use routes::init_routes;
-use std::env;
+use std::{env, io, net::TcpListener};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL cannot be empty (env)");
-    let pool = get_db_pool(&database_url);
-    let db_addr = SyncArbiter::start(5, move || DbActor(pool.clone()));
-    let chat_server = ChatServer::new().start();
-    let jackpot_server = JackpotServer::new().start();
-    // let crash_server = CrashServer::new().start();
-    let coinflip_server = CoinflipServer::new().start();
-    HttpServer::new(move || {
-        App::new()
-            .wrap(
-                Cors::default()
-                    .allowed_origin("http://127.0.0.1:5500")
-                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-                    .allowed_headers(vec![
-                        header::AUTHORIZATION,
-                        header::ACCEPT,
-                        header::CONTENT_TYPE,
-                    ])
-                    .supports_credentials()
-                    .max_age(3600),
-            )
-            .app_data(Data::new(jackpot_server.clone()))
-            .app_data(Data::new(chat_server.clone()))
-            // .app_data(Data::new(crash_server.clone()))
+    let command = Cli::parse().command.unwrap_or(Command::Serve);
+    let settings = Settings::load().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    match command {
+        Command::Serve => telemetry::init(&settings.log),
+        _ => telemetry::init_stderr(&settings.log),
+    }
+    let repos = open_repositories(&settings, &command)?;
+
+    match command {
+        Command::Serve => {
+            let listener = TcpListener::bind((settings.server.host.clone(), settings.server.port))?;
+            let servers = GameServers::start(&settings, &repos).await;
+            server::serve(settings, repos, servers, listener)?.await
+        }
+        Command::Migrate => Ok(()),
+        command => cli::run(command, repos).await.map_err(|e| {
+            tracing::error!(error = %e, "Command failed");
+            io::Error::other(e)
+        }),
+    }
+}
 
-            .app_data(Data::new(coinflip_server.clone()))
-            .app_data(Data::new(AppState {
-                db: db_addr.clone(),
-            }))
-            .configure(init_routes)
-    })
-    .bind(("127.0.0.1", 8081))?
-    .run()
-    .await
+fn open_repositories(settings: &Settings, command: &Command) -> io::Result<Repositories> {
+    if settings.database.backend == StorageBackend::Memory {
+        //* Admin commands would only ever see an empty store */
+        if !matches!(command, Command::Serve) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The memory backend only supports serve",
+            ));
+        }
+        tracing::warn!("Using the in-memory backend, nothing will be persisted");
+        return Ok(Repositories::memory());
+    }
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL cannot be empty (env)");
+    let pool = get_db_pool(
+        &database_url,
+        settings.database.pool_size,
+        settings.database.connection_timeout(),
+    );
+    let auto_migrate = command.runs_migrations(settings.database.auto_migrate);
+    migrations::prepare(&pool, auto_migrate).map_err(|e| {
+        tracing::error!(error = %e, "Database is not ready");
+        io::Error::other(e)
+    })?;
+    Ok(Repositories::postgres(pool))
 }
@@ -0,0 +1,6 @@
+use actix::Message;
+
+//* Answered by every game server as long as its mailbox is being processed */
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Ping;
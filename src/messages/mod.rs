@@ -1 +1 @@
-pub mod auth;
+pub mod health;
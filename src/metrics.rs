@@ -0,0 +1,133 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    exponential_buckets, CounterVec, Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec,
+    IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+//* Process wide so actors, sockets and the repositories can record without carrying a handle around */
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    pub ws_connections: IntGaugeVec,
+    pub rounds: IntCounterVec,
+    //* Money metrics only cover wallet backed games, crash bets aren't taken from a wallet yet */
+    pub bets: IntCounterVec,
+    pub wagered: CounterVec,
+    pub payouts: CounterVec,
+    //* Rake plus house bot results, so it can go down */
+    pub house_profit: GaugeVec,
+    pub db_pending_requests: IntGauge,
+    pub db_request_duration: HistogramVec,
+    pub logins: IntCounterVec,
+    //* How much later than scheduled a crash tick ran */
+    pub crash_tick_lag: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("jackpot".to_string()), None).expect("valid metrics prefix");
+        let metrics = Metrics {
+            ws_connections: IntGaugeVec::new(
+                Opts::new("ws_connections", "Open websocket connections"),
+                &["channel"],
+            )
+            .expect("valid metric"),
+            rounds: IntCounterVec::new(
+                Opts::new("rounds_total", "Rounds played to completion"),
+                &["game"],
+            )
+            .expect("valid metric"),
+            bets: IntCounterVec::new(Opts::new("bets_total", "Bets placed"), &["game"])
+                .expect("valid metric"),
+            wagered: CounterVec::new(
+                Opts::new("wagered_total", "Amount wagered since start"),
+                &["game"],
+            )
+            .expect("valid metric"),
+            payouts: CounterVec::new(
+                Opts::new("payouts_total", "Amount paid out to winners since start"),
+                &["game"],
+            )
+            .expect("valid metric"),
+            house_profit: GaugeVec::new(
+                Opts::new("house_profit", "House profit since start"),
+                &["game"],
+            )
+            .expect("valid metric"),
+            db_pending_requests: IntGauge::new(
+                "db_pending_requests",
+                "Database requests waiting for a connection",
+            )
+            .expect("valid metric"),
+            db_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "db_request_duration_seconds",
+                    "Time a database request held its connection",
+                ),
+                &["operation"],
+            )
+            .expect("valid metric"),
+            logins: IntCounterVec::new(Opts::new("logins_total", "Login attempts"), &["outcome"])
+                .expect("valid metric"),
+            crash_tick_lag: Histogram::with_opts(
+                HistogramOpts::new("crash_tick_lag_seconds", "Delay of crash game ticks")
+                    .buckets(exponential_buckets(0.001, 2.0, 12).expect("valid buckets")),
+            )
+            .expect("valid metric"),
+            registry,
+        };
+        metrics.register();
+        metrics
+    }
+
+    fn register(&self) {
+        let collectors: [Box<dyn prometheus::core::Collector>; 10] = [
+            Box::new(self.ws_connections.clone()),
+            Box::new(self.rounds.clone()),
+            Box::new(self.bets.clone()),
+            Box::new(self.wagered.clone()),
+            Box::new(self.payouts.clone()),
+            Box::new(self.house_profit.clone()),
+            Box::new(self.db_pending_requests.clone()),
+            Box::new(self.db_request_duration.clone()),
+            Box::new(self.logins.clone()),
+            Box::new(self.crash_tick_lag.clone()),
+        ];
+        for collector in collectors {
+            self.registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+    }
+
+    //* Prometheus text exposition format */
+    pub fn encode(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+
+    pub fn record_bet(&self, game: &str, amount: f64) {
+        self.bets.with_label_values(&[game]).inc();
+        self.wagered.with_label_values(&[game]).inc_by(amount);
+    }
+
+    pub fn record_payout(&self, game: &str, amount: f64) {
+        self.payouts.with_label_values(&[game]).inc_by(amount);
+    }
+
+    pub fn record_round(&self, game: &str) {
+        self.rounds.with_label_values(&[game]).inc();
+    }
+
+    pub fn record_house_profit(&self, game: &str, amount: f64) {
+        self.house_profit.with_label_values(&[game]).add(amount);
+    }
+}
@@ -1 +1,3 @@
 pub mod only_authorized;
+pub mod rate_limit;
+pub mod request_span;
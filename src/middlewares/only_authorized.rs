@@ -50,20 +50,12 @@ where
                 if let Ok(claims) = decoded {
                     req.extensions_mut().insert(claims);
                     let fut = self.service.call(req);
-                    Box::pin(async move { fut.await })
+                    Box::pin(fut)
                 } else {
-                    Box::pin(async {
-                        Err(Error::from(actix_web::error::ErrorUnauthorized(
-                            "Not Authorized!",
-                        )))
-                    })
+                    Box::pin(async { Err(actix_web::error::ErrorUnauthorized("Not Authorized!")) })
                 }
             }
-            None => Box::pin(async {
-                Err(Error::from(actix_web::error::ErrorUnauthorized(
-                    "Not Authorized!",
-                )))
-            }),
+            None => Box::pin(async { Err(actix_web::error::ErrorUnauthorized("Not Authorized!")) }),
         }
     }
 }
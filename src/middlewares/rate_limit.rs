@@ -0,0 +1,107 @@
+use std::{
+    future::{ready, Ready},
+    net::IpAddr,
+};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+    web::Data,
+    Error, HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use serde_json::json;
+
+use crate::rate_limit::{IpRateLimiter, RateLimiters};
+
+//* Limits a route per client IP, the limiter is picked from the shared RateLimiters app data */
+pub struct IpRateLimit {
+    limiter: fn(&RateLimiters) -> &IpRateLimiter,
+}
+
+impl IpRateLimit {
+    pub fn new(limiter: fn(&RateLimiters) -> &IpRateLimiter) -> Self {
+        IpRateLimit { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for IpRateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IpRateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IpRateLimitMiddleware {
+            service,
+            limiter: self.limiter,
+        }))
+    }
+}
+
+pub struct IpRateLimitMiddleware<S> {
+    service: S,
+    limiter: fn(&RateLimiters) -> &IpRateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for IpRateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let limited = req.app_data::<Data<RateLimiters>>().and_then(|limiters| {
+            let ip = client_ip(&req, limiters.trusted_proxy_header.as_deref())?;
+            (self.limiter)(limiters).check(ip).err()
+        });
+
+        match limited {
+            Some(retry_after) => {
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((
+                        header::RETRY_AFTER,
+                        retry_after.as_secs_f64().ceil().max(1.0).to_string(),
+                    ))
+                    .json(json!({
+                        "message": "Too many requests",
+                        "status": 429,
+                        "variant": "TooManyRequests",
+                    }));
+                Box::pin(async move { Ok(req.into_response(response).map_into_right_body()) })
+            }
+            None => {
+                let fut = self.service.call(req);
+                Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+            }
+        }
+    }
+}
+
+//* The proxy header is only trusted when configured; its last entry is the one the proxy added */
+fn client_ip(req: &ServiceRequest, proxy_header: Option<&str>) -> Option<IpAddr> {
+    if let Some(name) = proxy_header {
+        let forwarded = req
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    req.peer_addr().map(|addr| addr.ip())
+}
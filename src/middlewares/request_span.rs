@@ -0,0 +1,76 @@
+use std::{
+    future::{ready, Ready},
+    time::Instant,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures::future::LocalBoxFuture;
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+//* Runs every request inside an http_request span; only the path is recorded since websocket tokens travel in the query string */
+pub struct RequestSpan;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestSpan
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestSpanMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestSpanMiddleware { service }))
+    }
+}
+
+pub struct RequestSpanMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestSpanMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let span = info_span!(
+            "http_request",
+            method = %req.method(),
+            path = %req.path(),
+            request_id = %Uuid::new_v4(),
+        );
+        let started = Instant::now();
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let res = fut.await;
+                let elapsed_ms = started.elapsed().as_millis() as u64;
+                match &res {
+                    Ok(response) => tracing::info!(
+                        status = response.status().as_u16(),
+                        elapsed_ms,
+                        "Request completed"
+                    ),
+                    Err(e) => tracing::warn!(error = %e, elapsed_ms, "Request failed"),
+                }
+                res
+            }
+            .instrument(span),
+        )
+    }
+}
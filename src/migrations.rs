@@ -0,0 +1,44 @@
+use diesel::pg::PgConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use crate::{db_utils::AppDbType, errors::migration::MigrationError};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+//* Applies everything the database hasn't seen yet and returns the applied versions */
+pub fn run_pending(conn: &mut PgConnection) -> Result<Vec<String>, MigrationError> {
+    let applied = conn
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(MigrationError::Failed)?;
+    Ok(applied.iter().map(|version| version.to_string()).collect())
+}
+
+pub fn ensure_up_to_date(conn: &mut PgConnection) -> Result<(), MigrationError> {
+    let pending = conn
+        .pending_migrations(MIGRATIONS)
+        .map_err(MigrationError::Failed)?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    Err(MigrationError::Pending(
+        pending
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .collect(),
+    ))
+}
+
+//* Runs before any actor touches the database */
+pub fn prepare(pool: &AppDbType, auto_migrate: bool) -> Result<(), MigrationError> {
+    let mut conn = pool.get()?;
+    if !auto_migrate {
+        return ensure_up_to_date(&mut conn);
+    }
+    let applied = run_pending(&mut conn)?;
+    if applied.is_empty() {
+        tracing::info!("Database schema is up to date");
+    } else {
+        tracing::info!(migrations = ?applied, "Applied migrations");
+    }
+    Ok(())
+}
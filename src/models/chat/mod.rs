@@ -0,0 +1,63 @@
+use crate::schema::{chat_messages, chat_moderation_actions};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = chat_messages)]
+pub struct ChatMessage {
+    pub id: i32,
+    pub message_id: String,
+    pub user_id: i32,
+    pub username: String,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub room: String,
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = chat_messages)]
+pub struct NewChatMessage {
+    pub message_id: String,
+    pub user_id: i32,
+    pub username: String,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+    pub room: String,
+}
+
+pub const ACTION_DELETE: &str = "delete";
+pub const ACTION_MUTE: &str = "mute";
+pub const ACTION_UNMUTE: &str = "unmute";
+pub const ACTION_BAN: &str = "ban";
+pub const ACTION_UNBAN: &str = "unban";
+pub const ACTION_SLOW_MODE: &str = "slow_mode";
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = chat_moderation_actions)]
+pub struct ModerationAction {
+    pub id: i32,
+    pub action: String,
+    pub moderator_id: i32,
+    pub target_user_id: Option<i32>,
+    pub room: Option<String>,
+    pub message_id: Option<String>,
+    pub duration_secs: Option<i64>,
+    pub reason: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug, Default)]
+#[diesel(table_name = chat_moderation_actions)]
+pub struct NewModerationAction {
+    pub action: String,
+    pub moderator_id: i32,
+    pub target_user_id: Option<i32>,
+    pub room: Option<String>,
+    pub message_id: Option<String>,
+    pub duration_secs: Option<i64>,
+    pub reason: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,34 @@
+use crate::schema::jackpot_rounds;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = jackpot_rounds)]
+pub struct JackpotRound {
+    pub id: i32,
+    pub round_id: String,
+    pub winner_id: i32,
+    pub winner_name: String,
+    pub pot: f64,
+    pub house_fee: f64,
+    pub payout: f64,
+    pub winning_ticket: i64,
+    pub total_tickets: i64,
+    pub player_count: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = jackpot_rounds)]
+pub struct NewJackpotRound {
+    pub round_id: String,
+    pub winner_id: i32,
+    pub winner_name: String,
+    pub pot: f64,
+    pub house_fee: f64,
+    pub payout: f64,
+    pub winning_ticket: i64,
+    pub total_tickets: i64,
+    pub player_count: i32,
+}
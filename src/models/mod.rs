@@ -1 +1,5 @@
+pub mod chat;
+pub mod jackpot;
+pub mod round;
 pub mod user;
+pub mod wallet;
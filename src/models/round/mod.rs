@@ -0,0 +1,60 @@
+use crate::models::jackpot::JackpotRound;
+use crate::schema::{game_rounds, round_bets};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const STATUS_OPEN: &str = "open";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SETTLED: &str = "settled";
+pub const STATUS_REFUNDED: &str = "refunded";
+pub const STATUS_VOIDED: &str = "voided";
+
+//* A round in one of these states never changes again */
+pub const FINAL_STATUSES: [&str; 3] = [STATUS_SETTLED, STATUS_REFUNDED, STATUS_VOIDED];
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = game_rounds)]
+pub struct GameRound {
+    pub id: i32,
+    pub game: String,
+    pub round_id: String,
+    pub status: String,
+    pub refunded: f64,
+    //* Set when the round was found unfinished on startup */
+    pub recovered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = game_rounds)]
+pub struct NewGameRound {
+    pub game: String,
+    pub round_id: String,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = round_bets)]
+pub struct NewRoundBet {
+    pub round_id: String,
+    pub user_id: i32,
+    pub amount: f64,
+}
+
+//* What the journal, the ledger and the round history say about one round */
+#[derive(Serialize, Debug)]
+pub struct RoundAudit {
+    pub round: GameRound,
+    pub journaled_bets: f64,
+    //* Ledger bets net of the refunds, i.e. what the round still holds */
+    pub staked: f64,
+    pub refunded: f64,
+    pub paid_out: f64,
+    pub raked: f64,
+    pub jackpot: Option<JackpotRound>,
+    //* Empty when every source agrees */
+    pub issues: Vec<String>,
+}
@@ -3,14 +3,29 @@ use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Queryable, Selectable)]
+pub const ROLE_USER: &str = "user";
+pub const ROLE_HOUSE: &str = "house";
+pub const ROLE_MODERATOR: &str = "moderator";
+pub const ROLE_ADMIN: &str = "admin";
+
+//* Stored instead of a bcrypt hash for accounts nobody logs into, such as the house */
+pub const NO_PASSWORD: &str = "!";
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Clone)]
 #[diesel(table_name = users)]
 pub struct User {
     pub id: i32,
     pub username: String,
     pub hashed_password: String,
-    pub balance: i32,
+    pub balance: f64,
     pub created_at: NaiveDateTime,
+    pub role: String,
+}
+
+impl User {
+    pub fn can_moderate(&self) -> bool {
+        self.role == ROLE_MODERATOR || self.role == ROLE_ADMIN
+    }
 }
 
 #[derive(Insertable, Serialize, Debug, Deserialize)]
@@ -18,5 +33,6 @@ pub struct User {
 pub struct NewUser {
     pub username: String,
     pub hashed_password: String,
-    pub balance: i32,
+    pub balance: f64,
+    pub role: String,
 }
@@ -0,0 +1,37 @@
+use crate::schema::ledger_entries;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const REASON_COINFLIP_BET: &str = "coinflip_bet";
+pub const REASON_COINFLIP_PAYOUT: &str = "coinflip_payout";
+pub const REASON_COINFLIP_REFUND: &str = "coinflip_refund";
+pub const REASON_JACKPOT_BET: &str = "jackpot_bet";
+pub const REASON_JACKPOT_PAYOUT: &str = "jackpot_payout";
+pub const REASON_JACKPOT_RAKE: &str = "jackpot_rake";
+pub const REASON_JACKPOT_REFUND: &str = "jackpot_refund";
+pub const REASON_CHAT_TIP: &str = "chat_tip";
+pub const REASON_CHAT_RAIN: &str = "chat_rain";
+pub const REASON_ADMIN_ADJUSTMENT: &str = "admin_adjustment";
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = ledger_entries)]
+pub struct LedgerEntry {
+    pub id: i32,
+    pub user_id: i32,
+    pub amount: f64,
+    pub balance_after: f64,
+    pub reason: String,
+    pub reference: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = ledger_entries)]
+pub struct NewLedgerEntry {
+    pub user_id: i32,
+    pub amount: f64,
+    pub balance_after: f64,
+    pub reason: String,
+    pub reference: Option<String>,
+}
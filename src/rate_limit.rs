@@ -0,0 +1,425 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix::{Actor, ActorContext};
+use actix_web_actors::ws;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    ChatRateLimits, CoinflipRateLimits, CrashRateLimits, JackpotRateLimits, RateLimitSettings,
+};
+
+//* Violations older than this are forgotten */
+const VIOLATION_WINDOW: Duration = Duration::from_secs(60);
+//* Shared buckets are pruned once there are this many of them */
+const MAX_SHARED_BUCKETS: usize = 10_000;
+
+//* `burst` requests at once, refilled at `per_second`; parsed from "burst:per_second" */
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RateLimit {
+    pub burst: f64,
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    pub fn new(burst: f64, per_second: f64) -> Self {
+        RateLimit { burst, per_second }
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (burst, per_second) = s.split_once(':').ok_or(())?;
+        let burst: f64 = burst.trim().parse().map_err(|_| ())?;
+        let per_second: f64 = per_second.trim().parse().map_err(|_| ())?;
+        if burst < 1.0 || per_second <= 0.0 {
+            return Err(());
+        }
+        Ok(RateLimit::new(burst, per_second))
+    }
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .parse()
+            .map_err(|_| format!("invalid rate limit {value:?}, expected \"burst:per_second\""))
+    }
+}
+
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        TokenBucket {
+            limit,
+            tokens: limit.burst,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst);
+        self.refilled_at = now;
+    }
+
+    fn try_take(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.limit.burst
+    }
+
+    //* Time until the next token, zero when one is available */
+    fn wait_time(&self) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.limit.per_second).max(0.0))
+    }
+}
+
+//* Lets the limiter read any of the [rate_limit.<game>] sections */
+pub trait CommandLimits: Send + Sync {
+    fn connection(&self) -> RateLimit;
+    fn max_violations(&self) -> usize;
+    //* None for commands that only count against the connection */
+    fn command(&self, command: &str) -> Option<RateLimit>;
+}
+
+impl CommandLimits for JackpotRateLimits {
+    fn connection(&self) -> RateLimit {
+        self.connection
+    }
+
+    fn max_violations(&self) -> usize {
+        self.max_violations
+    }
+
+    fn command(&self, command: &str) -> Option<RateLimit> {
+        match command {
+            "deposit" => Some(self.deposit),
+            "join_room" => Some(self.join_room),
+            _ => None,
+        }
+    }
+}
+
+impl CommandLimits for CrashRateLimits {
+    fn connection(&self) -> RateLimit {
+        self.connection
+    }
+
+    fn max_violations(&self) -> usize {
+        self.max_violations
+    }
+
+    fn command(&self, command: &str) -> Option<RateLimit> {
+        match command {
+            "deposit" => Some(self.deposit),
+            "cashout" => Some(self.cashout),
+            _ => None,
+        }
+    }
+}
+
+impl CommandLimits for CoinflipRateLimits {
+    fn connection(&self) -> RateLimit {
+        self.connection
+    }
+
+    fn max_violations(&self) -> usize {
+        self.max_violations
+    }
+
+    fn command(&self, command: &str) -> Option<RateLimit> {
+        match command {
+            "create" => Some(self.create),
+            "join" => Some(self.join),
+            _ => None,
+        }
+    }
+}
+
+impl CommandLimits for ChatRateLimits {
+    fn connection(&self) -> RateLimit {
+        self.connection
+    }
+
+    fn max_violations(&self) -> usize {
+        self.max_violations
+    }
+
+    fn command(&self, command: &str) -> Option<RateLimit> {
+        match command {
+            "message" => Some(self.message),
+            "command" => Some(self.command),
+            "join" => Some(self.join),
+            "moderate" => Some(self.moderate),
+            _ => None,
+        }
+    }
+}
+
+//* Per user, per command buckets of one game, shared by all of its connections */
+pub struct RateLimiter {
+    limits: Box<dyn CommandLimits>,
+    users: Mutex<HashMap<(i32, String), TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: impl CommandLimits + 'static) -> Self {
+        RateLimiter {
+            limits: Box::new(limits),
+            users: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn allow_user(&self, user_id: i32, command: &str) -> bool {
+        let Some(limit) = self.limits.command(command) else {
+            return true;
+        };
+        let Ok(mut users) = self.users.lock() else {
+            return true;
+        };
+        if users.len() >= MAX_SHARED_BUCKETS {
+            users.retain(|_, bucket| !bucket.is_full());
+        }
+        users
+            .entry((user_id, command.to_string()))
+            .or_insert_with(|| TokenBucket::new(limit))
+            .try_take()
+    }
+}
+
+//* Buckets keyed by client IP, used for HTTP routes */
+pub struct IpRateLimiter {
+    limit: RateLimit,
+    clients: Mutex<HashMap<IpAddr, TokenBucket>>,
+}
+
+impl IpRateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        IpRateLimiter {
+            limit,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    //* The error holds how long the client has to wait before retrying */
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let Ok(mut clients) = self.clients.lock() else {
+            return Ok(());
+        };
+        if clients.len() >= MAX_SHARED_BUCKETS {
+            clients.retain(|_, bucket| !bucket.is_full());
+        }
+        let bucket = clients
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::new(self.limit));
+        if bucket.try_take() {
+            Ok(())
+        } else {
+            Err(bucket.wait_time())
+        }
+    }
+}
+
+pub struct RateLimiters {
+    pub jackpot: Arc<RateLimiter>,
+    pub crash: Arc<RateLimiter>,
+    pub coinflip: Arc<RateLimiter>,
+    pub chat: Arc<RateLimiter>,
+    pub login: IpRateLimiter,
+    pub register: IpRateLimiter,
+    //* Header set by a trusted reverse proxy with the client IP, e.g. X-Forwarded-For */
+    pub trusted_proxy_header: Option<String>,
+}
+
+impl RateLimiters {
+    pub fn from_settings(settings: &RateLimitSettings) -> Self {
+        RateLimiters {
+            jackpot: Arc::new(RateLimiter::new(settings.jackpot)),
+            crash: Arc::new(RateLimiter::new(settings.crash)),
+            coinflip: Arc::new(RateLimiter::new(settings.coinflip)),
+            chat: Arc::new(RateLimiter::new(settings.chat)),
+            login: IpRateLimiter::new(settings.login),
+            register: IpRateLimiter::new(settings.register),
+            trusted_proxy_header: settings
+                .trusted_proxy_header
+                .clone()
+                .filter(|header| !header.is_empty()),
+        }
+    }
+}
+
+pub enum RateLimitDecision {
+    Allowed,
+    Limited,
+    //* Too many violations, the socket should be closed */
+    Exceeded,
+}
+
+//* Lives in a websocket actor, one per connection */
+pub struct ConnectionLimiter {
+    shared: Arc<RateLimiter>,
+    connection: TokenBucket,
+    //* Per command buckets of anonymous sockets, whose ids are not stable across connections */
+    commands: HashMap<String, TokenBucket>,
+    violations: Vec<Instant>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(shared: Arc<RateLimiter>) -> Self {
+        ConnectionLimiter {
+            connection: TokenBucket::new(shared.limits.connection()),
+            shared,
+            commands: HashMap::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    //* `user_id` is None for anonymous sockets, their command buckets live on the connection */
+    pub fn check(&mut self, user_id: Option<i32>, command: &str) -> RateLimitDecision {
+        let allowed = self.connection.try_take()
+            && match user_id {
+                Some(user_id) => self.shared.allow_user(user_id, command),
+                None => self.allow_anonymous(command),
+            };
+        if allowed {
+            return RateLimitDecision::Allowed;
+        }
+
+        let now = Instant::now();
+        self.violations
+            .retain(|at| now.duration_since(*at) < VIOLATION_WINDOW);
+        self.violations.push(now);
+        if self.violations.len() >= self.shared.limits.max_violations() {
+            RateLimitDecision::Exceeded
+        } else {
+            RateLimitDecision::Limited
+        }
+    }
+
+    //* Returns false when the command has to be dropped: a limited socket is sent `rejection`, one with too many violations is closed */
+    pub fn throttle<A, T>(
+        &mut self,
+        user_id: Option<i32>,
+        command: &str,
+        rejection: impl FnOnce() -> T,
+        ctx: &mut ws::WebsocketContext<A>,
+    ) -> bool
+    where
+        A: Actor<Context = ws::WebsocketContext<A>>,
+        T: Serialize,
+    {
+        match self.check(user_id, command) {
+            RateLimitDecision::Allowed => true,
+            RateLimitDecision::Limited => {
+                if let Ok(json) = serde_json::to_string(&rejection()) {
+                    ctx.text(json);
+                }
+                false
+            }
+            RateLimitDecision::Exceeded => {
+                ctx.close(Some(close_reason()));
+                ctx.stop();
+                false
+            }
+        }
+    }
+
+    fn allow_anonymous(&mut self, command: &str) -> bool {
+        let Some(limit) = self.shared.limits.command(command) else {
+            return true;
+        };
+        self.commands
+            .entry(command.to_string())
+            .or_insert_with(|| TokenBucket::new(limit))
+            .try_take()
+    }
+}
+
+pub const RATE_LIMITED_MESSAGE: &str = "Too many requests, slow down";
+
+fn close_reason() -> ws::CloseReason {
+    ws::CloseReason {
+        code: ws::CloseCode::Policy,
+        description: Some("Rate limit exceeded".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elapse(bucket: &mut TokenBucket, secs: f64) {
+        bucket.refilled_at -= Duration::from_secs_f64(secs);
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_refuses() {
+        let mut bucket = TokenBucket::new(RateLimit::new(3.0, 0.001));
+        assert!((0..3).all(|_| bucket.try_take()));
+        assert!(!bucket.try_take());
+        assert!(bucket.wait_time() > Duration::from_secs(100));
+    }
+
+    #[test]
+    fn bucket_refills_up_to_the_burst() {
+        let mut bucket = TokenBucket::new(RateLimit::new(2.0, 1.0));
+        assert!(bucket.try_take() && bucket.try_take());
+        assert!(!bucket.try_take());
+
+        elapse(&mut bucket, 1.0);
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+
+        elapse(&mut bucket, 60.0);
+        assert!(bucket.is_full());
+        assert!(bucket.try_take() && bucket.try_take());
+        assert!(!bucket.try_take());
+    }
+
+    #[test]
+    fn parses_rate_limits() {
+        let limit: RateLimit = " 5 : 0.5 ".parse().unwrap();
+        assert_eq!((limit.burst, limit.per_second), (5.0, 0.5));
+        for spec in ["", "5", "5:", "five:1", "0.5:1", "5:0", "5:-1"] {
+            assert!(spec.parse::<RateLimit>().is_err(), "{spec:?}");
+        }
+    }
+
+    #[test]
+    fn command_limits_are_per_user() {
+        let limiter = RateLimiter::new(CoinflipRateLimits {
+            create: RateLimit::new(1.0, 0.001),
+            ..CoinflipRateLimits::default()
+        });
+        assert!(limiter.allow_user(1, "create"));
+        assert!(!limiter.allow_user(1, "create"));
+        assert!(limiter.allow_user(2, "create"));
+        assert!((0..100).all(|_| limiter.allow_user(1, "list")));
+    }
+}
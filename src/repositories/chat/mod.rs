@@ -0,0 +1,25 @@
+use crate::{
+    errors::chat::ChatHistoryError,
+    models::chat::{ChatMessage, ModerationAction, NewChatMessage, NewModerationAction},
+};
+
+use super::StorageFuture;
+
+pub trait ChatRepository: Send + Sync {
+    fn save(&self, message: NewChatMessage) -> StorageFuture<ChatMessage, ChatHistoryError>;
+    //* Newest messages of a room first, older than `before_id` when it is set */
+    fn history(
+        &self,
+        room: String,
+        before_id: Option<i32>,
+        limit: i64,
+    ) -> StorageFuture<Vec<ChatMessage>, ChatHistoryError>;
+    //* Soft deletes the message so it no longer shows up in history */
+    fn delete(&self, message_id: String) -> StorageFuture<ChatMessage, ChatHistoryError>;
+    fn record_moderation(
+        &self,
+        action: NewModerationAction,
+    ) -> StorageFuture<ModerationAction, ChatHistoryError>;
+    //* Every mute, ban and slow mode change that has not expired yet, oldest first so they can be replayed in order */
+    fn active_moderation(&self) -> StorageFuture<Vec<ModerationAction>, ChatHistoryError>;
+}
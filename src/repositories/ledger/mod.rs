@@ -0,0 +1,35 @@
+use chrono::NaiveDateTime;
+
+use crate::{errors::wallet::WalletError, models::wallet::LedgerEntry};
+
+use super::StorageFuture;
+
+//* Credits (positive amount) or debits (negative amount) a user's balance and records it in the ledger */
+pub struct ApplyLedgerEntry {
+    pub user_id: i32,
+    pub amount: f64,
+    pub reason: String,
+    pub reference: Option<String>,
+}
+
+//* Moves balance from one user to the recipients in a single transaction, every side gets a ledger entry */
+pub struct Transfer {
+    pub from_user_id: i32,
+    //* (user id, amount) pairs, the sender is debited their sum */
+    pub recipients: Vec<(i32, f64)>,
+    pub reason: String,
+    pub reference: Option<String>,
+}
+
+pub trait LedgerRepository: Send + Sync {
+    fn apply(&self, entry: ApplyLedgerEntry) -> StorageFuture<LedgerEntry, WalletError>;
+    fn transfer(&self, transfer: Transfer) -> StorageFuture<Vec<LedgerEntry>, WalletError>;
+    //* Entries created in [from, to), in id order and paged by the last id seen */
+    fn entries(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        after_id: i32,
+        limit: i64,
+    ) -> StorageFuture<Vec<LedgerEntry>, WalletError>;
+}
@@ -0,0 +1,101 @@
+use crate::{
+    errors::chat::ChatHistoryError,
+    models::chat::{
+        ChatMessage, ModerationAction, NewChatMessage, NewModerationAction, ACTION_DELETE,
+    },
+    repositories::{chat::ChatRepository, StorageFuture},
+};
+
+use super::{next_id, now, unique_violation, MemoryStore};
+
+impl ChatRepository for MemoryStore {
+    fn save(&self, message: NewChatMessage) -> StorageFuture<ChatMessage, ChatHistoryError> {
+        self.run(move |state| {
+            if state
+                .messages
+                .iter()
+                .any(|m| m.message_id == message.message_id)
+            {
+                return Err(unique_violation("chat_messages_message_id_key").into());
+            }
+            let message = ChatMessage {
+                id: next_id(state.messages.len()),
+                message_id: message.message_id,
+                user_id: message.user_id,
+                username: message.username,
+                message: message.message,
+                created_at: message.created_at,
+                room: message.room,
+                deleted_at: None,
+            };
+            state.messages.push(message.clone());
+            Ok(message)
+        })
+    }
+
+    fn history(
+        &self,
+        room: String,
+        before_id: Option<i32>,
+        limit: i64,
+    ) -> StorageFuture<Vec<ChatMessage>, ChatHistoryError> {
+        self.run(move |state| {
+            Ok(state
+                .messages
+                .iter()
+                .rev()
+                .filter(|m| m.room == room && m.deleted_at.is_none())
+                .filter(|m| before_id.is_none_or(|before_id| m.id < before_id))
+                .take(limit.max(0) as usize)
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn delete(&self, message_id: String) -> StorageFuture<ChatMessage, ChatHistoryError> {
+        self.run(move |state| {
+            let message = state
+                .messages
+                .iter_mut()
+                .find(|m| m.message_id == message_id && m.deleted_at.is_none())
+                .ok_or(ChatHistoryError::MessageNotFound)?;
+            message.deleted_at = Some(now());
+            Ok(message.clone())
+        })
+    }
+
+    fn record_moderation(
+        &self,
+        action: NewModerationAction,
+    ) -> StorageFuture<ModerationAction, ChatHistoryError> {
+        self.run(move |state| {
+            let action = ModerationAction {
+                id: next_id(state.moderation.len()),
+                action: action.action,
+                moderator_id: action.moderator_id,
+                target_user_id: action.target_user_id,
+                room: action.room,
+                message_id: action.message_id,
+                duration_secs: action.duration_secs,
+                reason: action.reason,
+                expires_at: action.expires_at,
+                created_at: now(),
+            };
+            state.moderation.push(action.clone());
+            Ok(action)
+        })
+    }
+
+    fn active_moderation(&self) -> StorageFuture<Vec<ModerationAction>, ChatHistoryError> {
+        self.run(move |state| {
+            let now = now();
+            Ok(state
+                .moderation
+                .iter()
+                .filter(|a| a.action != ACTION_DELETE)
+                .filter(|a| a.expires_at.is_none_or(|expires_at| expires_at > now))
+                .cloned()
+                .collect())
+        })
+    }
+}
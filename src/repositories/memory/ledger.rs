@@ -0,0 +1,103 @@
+use chrono::NaiveDateTime;
+
+use crate::{
+    errors::wallet::WalletError,
+    models::{user::ROLE_HOUSE, wallet::LedgerEntry},
+    repositories::{
+        ledger::{ApplyLedgerEntry, LedgerRepository, Transfer},
+        StorageFuture,
+    },
+};
+
+use super::{next_id, now, MemoryStore, State};
+
+impl LedgerRepository for MemoryStore {
+    fn apply(&self, entry: ApplyLedgerEntry) -> StorageFuture<LedgerEntry, WalletError> {
+        self.run(move |state| {
+            apply_ledger_entry(
+                state,
+                entry.user_id,
+                entry.amount,
+                entry.reason,
+                entry.reference,
+            )
+        })
+    }
+
+    fn transfer(&self, transfer: Transfer) -> StorageFuture<Vec<LedgerEntry>, WalletError> {
+        let total = transfer
+            .recipients
+            .iter()
+            .fold(0.0, |sum, (_, amount)| sum + amount);
+        let mut entries = vec![(transfer.from_user_id, -total)];
+        entries.extend(transfer.recipients);
+        //* Same entry order as the Postgres store, so ledger ids line up between the two */
+        entries.sort_by_key(|(user_id, _)| *user_id);
+
+        self.transaction(move |state| {
+            entries
+                .into_iter()
+                .map(|(user_id, amount)| {
+                    apply_ledger_entry(
+                        state,
+                        user_id,
+                        amount,
+                        transfer.reason.clone(),
+                        transfer.reference.clone(),
+                    )
+                })
+                .collect()
+        })
+    }
+
+    fn entries(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        after_id: i32,
+        limit: i64,
+    ) -> StorageFuture<Vec<LedgerEntry>, WalletError> {
+        self.run(move |state| {
+            Ok(state
+                .ledger
+                .iter()
+                .filter(|e| e.created_at >= from && e.created_at < to && e.id > after_id)
+                .take(limit.max(0) as usize)
+                .cloned()
+                .collect())
+        })
+    }
+}
+
+pub(super) fn apply_ledger_entry(
+    state: &mut State,
+    user_id: i32,
+    amount: f64,
+    reason: String,
+    reference: Option<String>,
+) -> Result<LedgerEntry, WalletError> {
+    let user = state
+        .users
+        .iter_mut()
+        .find(|u| u.id == user_id)
+        .ok_or(WalletError::UserNotFound)?;
+
+    //* The house is the counterparty of every game, so its balance is allowed to go negative */
+    let balance_after = user.balance + amount;
+    if balance_after < 0.0 && user.role != ROLE_HOUSE {
+        return Err(WalletError::InsufficientFunds);
+    }
+    user.balance = balance_after;
+
+    let entry = LedgerEntry {
+        id: next_id(state.ledger.len()),
+        user_id,
+        amount,
+        balance_after,
+        reason,
+        reference,
+        created_at: now(),
+    };
+    state.ledger.push(entry.clone());
+    Ok(entry)
+}
@@ -0,0 +1,323 @@
+mod chat;
+mod ledger;
+mod rounds;
+mod users;
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use futures::future;
+
+use crate::{
+    errors::health::HealthError,
+    models::{
+        chat::{ChatMessage, ModerationAction},
+        jackpot::JackpotRound,
+        round::GameRound,
+        user::User,
+        wallet::LedgerEntry,
+    },
+};
+
+use super::{Repositories, StorageFuture, StoreHealth};
+
+//* Keeps everything in process memory, for tests and for running the server without a database */
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+//* One Vec per table, ids are positions + 1 since rows are never removed */
+#[derive(Clone, Default)]
+struct State {
+    users: Vec<User>,
+    ledger: Vec<LedgerEntry>,
+    rounds: Vec<GameRound>,
+    //* (round id, user id, amount) */
+    bets: Vec<(String, i32, f64)>,
+    jackpot_rounds: Vec<JackpotRound>,
+    messages: Vec<ChatMessage>,
+    moderation: Vec<ModerationAction>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    //* Runs under the lock, so every call is atomic with respect to the others */
+    fn run<T, E, F>(&self, query: F) -> StorageFuture<T, E>
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+        F: FnOnce(&mut State) -> Result<T, E>,
+    {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        Box::pin(future::ready(query(&mut state)))
+    }
+
+    //* Like run, but nothing the query changed is kept when it fails, as in a database transaction */
+    fn transaction<T, E, F>(&self, query: F) -> StorageFuture<T, E>
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+        F: FnOnce(&mut State) -> Result<T, E>,
+    {
+        self.run(|state| {
+            let mut draft = state.clone();
+            let result = query(&mut draft)?;
+            *state = draft;
+            Ok(result)
+        })
+    }
+}
+
+impl StoreHealth for MemoryStore {
+    fn check(&self) -> StorageFuture<(), HealthError> {
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+impl Repositories {
+    pub fn memory() -> Self {
+        let store = Arc::new(MemoryStore::new());
+        Repositories {
+            users: store.clone(),
+            ledger: store.clone(),
+            rounds: store.clone(),
+            chat: store.clone(),
+            health: store,
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+fn next_id(len: usize) -> i32 {
+    len as i32 + 1
+}
+
+//* What Postgres reports for a duplicate in a UNIQUE column */
+fn unique_violation(constraint: &str) -> DieselError {
+    DieselError::DatabaseError(
+        DatabaseErrorKind::UniqueViolation,
+        Box::new(format!(
+            "duplicate key value violates unique constraint \"{constraint}\""
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use futures::executor::block_on;
+
+    use crate::{
+        errors::{auth::RegisterError, wallet::WalletError},
+        events::GAME_JACKPOT,
+        models::{
+            chat::NewChatMessage,
+            round::{STATUS_OPEN, STATUS_REFUNDED, STATUS_SETTLED},
+            user::{NewUser, User, ROLE_USER},
+            wallet::{REASON_CHAT_TIP, REASON_JACKPOT_BET},
+        },
+        repositories::{
+            ledger::{ApplyLedgerEntry, Transfer},
+            rounds::{JournalBet, JournalRound},
+        },
+    };
+
+    use super::*;
+
+    fn user(repos: &Repositories, name: &str, balance: f64) -> User {
+        block_on(repos.users.create(NewUser {
+            username: name.to_string(),
+            hashed_password: String::new(),
+            balance,
+            role: ROLE_USER.to_string(),
+        }))
+        .unwrap()
+    }
+
+    fn apply(repos: &Repositories, user_id: i32, amount: f64, reason: &str, reference: &str) {
+        block_on(repos.ledger.apply(ApplyLedgerEntry {
+            user_id,
+            amount,
+            reason: reason.to_string(),
+            reference: Some(reference.to_string()),
+        }))
+        .unwrap();
+    }
+
+    fn balance(repos: &Repositories, user_id: i32) -> f64 {
+        block_on(repos.users.find(user_id)).unwrap().balance
+    }
+
+    fn journal(repos: &Repositories, round_id: &str, status: &str) {
+        block_on(repos.rounds.journal_round(JournalRound {
+            game: GAME_JACKPOT.to_string(),
+            round_id: round_id.to_string(),
+            status: status.to_string(),
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn usernames_are_unique() {
+        let repos = Repositories::memory();
+        user(&repos, "alice", 0.0);
+        let duplicate = block_on(repos.users.create(NewUser {
+            username: "alice".to_string(),
+            hashed_password: String::new(),
+            balance: 0.0,
+            role: ROLE_USER.to_string(),
+        }));
+        assert!(matches!(
+            duplicate,
+            Err(RegisterError::UsernameAlreadyRegistered)
+        ));
+        let other_case = block_on(repos.users.create(NewUser {
+            username: "Alice".to_string(),
+            hashed_password: String::new(),
+            balance: 0.0,
+            role: ROLE_USER.to_string(),
+        }));
+        assert!(matches!(
+            other_case,
+            Err(RegisterError::UsernameAlreadyRegistered)
+        ));
+    }
+
+    #[test]
+    fn only_the_house_can_go_negative() {
+        let repos = Repositories::memory();
+        let alice = user(&repos, "alice", 5.0);
+        let house = block_on(repos.users.ensure_house("house".to_string())).unwrap();
+
+        let overdraft = block_on(repos.ledger.apply(ApplyLedgerEntry {
+            user_id: alice.id,
+            amount: -10.0,
+            reason: REASON_JACKPOT_BET.to_string(),
+            reference: None,
+        }));
+        assert!(matches!(overdraft, Err(WalletError::InsufficientFunds)));
+        assert_eq!(balance(&repos, alice.id), 5.0);
+
+        apply(&repos, house.id, -10.0, REASON_JACKPOT_BET, "r-1");
+        assert_eq!(balance(&repos, house.id), -10.0);
+        let again = block_on(repos.users.ensure_house("House".to_string())).unwrap();
+        assert_eq!(again.id, house.id);
+        let taken = block_on(repos.users.ensure_house("alice".to_string()));
+        assert!(matches!(taken, Err(WalletError::UserNotFound)));
+    }
+
+    #[test]
+    fn failed_transfers_change_nothing() {
+        let repos = Repositories::memory();
+        let alice = user(&repos, "alice", 10.0);
+        let bob = user(&repos, "bob", 0.0);
+
+        let transfer = block_on(repos.ledger.transfer(Transfer {
+            from_user_id: alice.id,
+            recipients: vec![(bob.id, 5.0), (42, 1.0)],
+            reason: REASON_CHAT_TIP.to_string(),
+            reference: None,
+        }));
+        assert!(matches!(transfer, Err(WalletError::UserNotFound)));
+        assert_eq!(balance(&repos, alice.id), 10.0);
+        assert_eq!(balance(&repos, bob.id), 0.0);
+
+        let entries = block_on(repos.ledger.transfer(Transfer {
+            from_user_id: alice.id,
+            recipients: vec![(bob.id, 4.0)],
+            reason: REASON_CHAT_TIP.to_string(),
+            reference: None,
+        }))
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(balance(&repos, alice.id), 6.0);
+        assert_eq!(balance(&repos, bob.id), 4.0);
+    }
+
+    #[test]
+    fn finished_rounds_are_not_reopened() {
+        let repos = Repositories::memory();
+        journal(&repos, "r-1", STATUS_OPEN);
+        journal(&repos, "r-1", STATUS_SETTLED);
+        journal(&repos, "r-1", STATUS_OPEN);
+
+        let audit = block_on(
+            repos
+                .rounds
+                .verify(GAME_JACKPOT.to_string(), "r-1".to_string()),
+        )
+        .unwrap();
+        assert_eq!(audit.round.status, STATUS_SETTLED);
+    }
+
+    #[test]
+    fn recovery_refunds_open_stakes() {
+        let repos = Repositories::memory();
+        let alice = user(&repos, "alice", 10.0);
+        let bob = user(&repos, "bob", 10.0);
+        journal(&repos, "r-1", STATUS_OPEN);
+        for (user_id, amount) in [(alice.id, 3.0), (bob.id, 2.0)] {
+            apply(&repos, user_id, -amount, REASON_JACKPOT_BET, "r-1");
+            block_on(repos.rounds.journal_bet(JournalBet {
+                round_id: "r-1".to_string(),
+                user_id,
+                amount,
+            }))
+            .unwrap();
+        }
+
+        let recovered = block_on(
+            repos
+                .rounds
+                .recover(GAME_JACKPOT.to_string(), now() + Duration::seconds(1)),
+        )
+        .unwrap();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].status, STATUS_REFUNDED);
+        assert_eq!(recovered[0].refunded, 5.0);
+        assert_eq!(balance(&repos, alice.id), 10.0);
+        assert_eq!(balance(&repos, bob.id), 10.0);
+
+        let audit = block_on(
+            repos
+                .rounds
+                .verify(GAME_JACKPOT.to_string(), "r-1".to_string()),
+        )
+        .unwrap();
+        assert!(audit.issues.is_empty(), "{:?}", audit.issues);
+        assert_eq!(block_on(repos.rounds.recovered(10, 0)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn history_is_newest_first_without_deleted_messages() {
+        let repos = Repositories::memory();
+        for (message_id, text) in [("m-1", "first"), ("m-2", "second"), ("m-3", "third")] {
+            block_on(repos.chat.save(NewChatMessage {
+                message_id: message_id.to_string(),
+                user_id: 1,
+                username: "alice".to_string(),
+                message: text.to_string(),
+                created_at: now(),
+                room: "global".to_string(),
+            }))
+            .unwrap();
+        }
+        block_on(repos.chat.delete("m-2".to_string())).unwrap();
+
+        let history = block_on(repos.chat.history("global".to_string(), None, 10)).unwrap();
+        let texts: Vec<&str> = history.iter().map(|m| m.message.as_str()).collect();
+        assert_eq!(texts, ["third", "first"]);
+
+        let older = block_on(repos.chat.history("global".to_string(), Some(3), 10)).unwrap();
+        assert_eq!(older.len(), 1);
+        assert!(block_on(repos.chat.delete("m-2".to_string())).is_err());
+    }
+}
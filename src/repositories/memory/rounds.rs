@@ -0,0 +1,234 @@
+use std::cmp::Reverse;
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    errors::{jackpot::RoundHistoryError, round::RoundJournalError},
+    models::{
+        jackpot::{JackpotRound, NewJackpotRound},
+        round::{
+            GameRound, RoundAudit, FINAL_STATUSES, STATUS_OPEN, STATUS_REFUNDED, STATUS_RUNNING,
+            STATUS_SETTLED, STATUS_VOIDED,
+        },
+    },
+    repositories::{
+        rounds::{
+            audit_round, ledger_reasons, open_stakes, JournalBet, JournalRound, RoundRepository,
+        },
+        StorageFuture,
+    },
+};
+
+use super::{ledger::apply_ledger_entry, next_id, now, unique_violation, MemoryStore, State};
+
+impl RoundRepository for MemoryStore {
+    fn journal_round(&self, round: JournalRound) -> StorageFuture<(), RoundJournalError> {
+        self.run(move |state| {
+            let now = now();
+            match state
+                .rounds
+                .iter_mut()
+                .find(|r| r.round_id == round.round_id)
+            {
+                //* A late update must not reopen a finished round */
+                Some(existing) => {
+                    if !FINAL_STATUSES.contains(&existing.status.as_str()) {
+                        existing.status = round.status;
+                        existing.updated_at = now;
+                    }
+                }
+                None => {
+                    let id = next_id(state.rounds.len());
+                    state.rounds.push(GameRound {
+                        id,
+                        game: round.game,
+                        round_id: round.round_id,
+                        status: round.status,
+                        refunded: 0.0,
+                        recovered_at: None,
+                        created_at: now,
+                        updated_at: now,
+                    });
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn journal_bet(&self, bet: JournalBet) -> StorageFuture<(), RoundJournalError> {
+        self.run(move |state| {
+            state.bets.push((bet.round_id, bet.user_id, bet.amount));
+            Ok(())
+        })
+    }
+
+    fn recover(
+        &self,
+        game: String,
+        started_before: NaiveDateTime,
+    ) -> StorageFuture<Vec<GameRound>, RoundJournalError> {
+        self.transaction(move |state| {
+            let unfinished: Vec<usize> = state
+                .rounds
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| {
+                    r.game == game
+                        && (r.status == STATUS_OPEN || r.status == STATUS_RUNNING)
+                        && r.created_at < started_before
+                })
+                .map(|(index, _)| index)
+                .collect();
+            unfinished
+                .into_iter()
+                .map(|index| recover_round(state, index))
+                .collect()
+        })
+    }
+
+    fn recovered(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> StorageFuture<Vec<GameRound>, RoundJournalError> {
+        self.run(move |state| {
+            let mut rounds: Vec<GameRound> = state
+                .rounds
+                .iter()
+                .filter(|r| r.recovered_at.is_some())
+                .cloned()
+                .collect();
+            rounds.sort_by_key(|r| Reverse(r.recovered_at));
+            Ok(rounds
+                .into_iter()
+                .skip(offset.max(0) as usize)
+                .take(limit.max(0) as usize)
+                .collect())
+        })
+    }
+
+    fn verify(&self, game: String, id: String) -> StorageFuture<RoundAudit, RoundJournalError> {
+        self.run(move |state| {
+            let round = state
+                .rounds
+                .iter()
+                .find(|r| r.game == game && r.round_id == id)
+                .cloned()
+                .ok_or(RoundJournalError::RoundNotFound)?;
+            let bets = state
+                .bets
+                .iter()
+                .filter(|(round_id, _, _)| *round_id == round.round_id)
+                .map(|(_, user_id, amount)| (*user_id, *amount))
+                .collect();
+            let entries = round_entries(state, &round.round_id);
+            let jackpot = state
+                .jackpot_rounds
+                .iter()
+                .find(|r| r.round_id == round.round_id)
+                .cloned();
+
+            Ok(audit_round(round, bets, entries, jackpot))
+        })
+    }
+
+    fn record_jackpot(
+        &self,
+        round: NewJackpotRound,
+    ) -> StorageFuture<JackpotRound, RoundHistoryError> {
+        self.run(move |state| {
+            if state
+                .jackpot_rounds
+                .iter()
+                .any(|r| r.round_id == round.round_id)
+            {
+                return Err(unique_violation("jackpot_rounds_round_id_key").into());
+            }
+            let round = JackpotRound {
+                id: next_id(state.jackpot_rounds.len()),
+                round_id: round.round_id,
+                winner_id: round.winner_id,
+                winner_name: round.winner_name,
+                pot: round.pot,
+                house_fee: round.house_fee,
+                payout: round.payout,
+                winning_ticket: round.winning_ticket,
+                total_tickets: round.total_tickets,
+                player_count: round.player_count,
+                created_at: now(),
+            };
+            state.jackpot_rounds.push(round.clone());
+            Ok(round)
+        })
+    }
+
+    fn jackpot_history(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> StorageFuture<Vec<JackpotRound>, RoundHistoryError> {
+        self.run(move |state| {
+            Ok(state
+                .jackpot_rounds
+                .iter()
+                .rev()
+                .skip(offset.max(0) as usize)
+                .take(limit.max(0) as usize)
+                .cloned()
+                .collect())
+        })
+    }
+}
+
+//* (user, amount, reason) of every ledger entry that references the round */
+fn round_entries(state: &State, round_id: &str) -> Vec<(i32, f64, String)> {
+    state
+        .ledger
+        .iter()
+        .filter(|e| e.reference.as_deref() == Some(round_id))
+        .map(|e| (e.user_id, e.amount, e.reason.clone()))
+        .collect()
+}
+
+//* Same rules as the Postgres store: refund what the ledger says is still staked, unless the round paid out */
+fn recover_round(state: &mut State, index: usize) -> Result<GameRound, RoundJournalError> {
+    let round_id = state.rounds[index].round_id.clone();
+    let mut refund_total = 0.0;
+    let outcome = match ledger_reasons(&state.rounds[index].game) {
+        None => STATUS_VOIDED,
+        Some((bet_reason, payout_reason, refund_reason)) => {
+            let entries = round_entries(state, &round_id);
+
+            if entries.iter().any(|(_, _, r)| r == payout_reason) {
+                STATUS_SETTLED
+            } else {
+                for (user, stake) in open_stakes(&entries, bet_reason, refund_reason) {
+                    if stake <= 0.0 {
+                        continue;
+                    }
+                    apply_ledger_entry(
+                        state,
+                        user,
+                        stake,
+                        refund_reason.to_string(),
+                        Some(round_id.clone()),
+                    )?;
+                    refund_total += stake;
+                }
+                if refund_total > 0.0 {
+                    STATUS_REFUNDED
+                } else {
+                    STATUS_VOIDED
+                }
+            }
+        }
+    };
+
+    let now = now();
+    let round = &mut state.rounds[index];
+    round.status = outcome.to_string();
+    round.refunded = refund_total;
+    round.recovered_at = Some(now);
+    round.updated_at = now;
+    Ok(round.clone())
+}
@@ -0,0 +1,86 @@
+use crate::{
+    errors::{
+        auth::{GetUserError, RegisterError},
+        wallet::WalletError,
+    },
+    models::user::{NewUser, User, NO_PASSWORD, ROLE_HOUSE},
+    repositories::{users::UserRepository, StorageFuture},
+};
+
+use super::{next_id, now, MemoryStore, State};
+
+fn insert(state: &mut State, user: NewUser) -> User {
+    let user = User {
+        id: next_id(state.users.len()),
+        username: user.username,
+        hashed_password: user.hashed_password,
+        balance: user.balance,
+        created_at: now(),
+        role: user.role,
+    };
+    state.users.push(user.clone());
+    user
+}
+
+//* Mirrors the unique index on lower(username) */
+fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+impl UserRepository for MemoryStore {
+    fn create(&self, user: NewUser) -> StorageFuture<User, RegisterError> {
+        self.run(move |state| {
+            if state
+                .users
+                .iter()
+                .any(|u| same_name(&u.username, &user.username))
+            {
+                return Err(RegisterError::UsernameAlreadyRegistered);
+            }
+            Ok(insert(state, user))
+        })
+    }
+
+    fn find(&self, user_id: i32) -> StorageFuture<User, GetUserError> {
+        self.run(move |state| {
+            state
+                .users
+                .iter()
+                .find(|u| u.id == user_id)
+                .cloned()
+                .ok_or(GetUserError::UserNotFound)
+        })
+    }
+
+    fn find_by_username(&self, name: String) -> StorageFuture<User, GetUserError> {
+        self.run(move |state| {
+            state
+                .users
+                .iter()
+                .find(|u| u.username == name)
+                .cloned()
+                .ok_or(GetUserError::UserNotFound)
+        })
+    }
+
+    fn ensure_house(&self, name: String) -> StorageFuture<User, WalletError> {
+        self.run(move |state| {
+            if let Some(user) = state.users.iter().find(|u| same_name(&u.username, &name)) {
+                //* A player holding the name is never used as the house */
+                if user.role != ROLE_HOUSE {
+                    return Err(WalletError::UserNotFound);
+                }
+                return Ok(user.clone());
+            }
+            Ok(insert(
+                state,
+                NewUser {
+                    username: name,
+                    hashed_password: NO_PASSWORD.to_string(),
+                    balance: 0.0,
+                    role: ROLE_HOUSE.to_string(),
+                },
+            ))
+        })
+    }
+}
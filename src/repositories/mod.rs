@@ -0,0 +1,47 @@
+pub mod chat;
+pub mod ledger;
+pub mod memory;
+pub mod postgres;
+pub mod rounds;
+pub mod users;
+
+use std::{fmt::Display, sync::Arc};
+
+use futures::future::BoxFuture;
+
+use crate::errors::health::HealthError;
+
+use self::{
+    chat::ChatRepository, ledger::LedgerRepository, rounds::RoundRepository, users::UserRepository,
+};
+
+//* Repository methods hand back owned futures so callers can keep them past the borrow, e.g. inside actor futures */
+pub type StorageFuture<T, E> = BoxFuture<'static, Result<T, E>>;
+
+pub trait StoreHealth: Send + Sync {
+    //* Resolves once the backend answered a trivial request */
+    fn check(&self) -> StorageFuture<(), HealthError>;
+}
+
+//* Everything the server persists, one repository per aggregate */
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub ledger: Arc<dyn LedgerRepository>,
+    pub rounds: Arc<dyn RoundRepository>,
+    pub chat: Arc<dyn ChatRepository>,
+    pub health: Arc<dyn StoreHealth>,
+}
+
+//* For writes nobody waits on, a failure is only logged */
+pub fn detach<T, E>(operation: &'static str, write: StorageFuture<T, E>)
+where
+    T: Send + 'static,
+    E: Display + Send + 'static,
+{
+    actix::spawn(async move {
+        if let Err(e) = write.await {
+            tracing::warn!(operation, error = %e, "Storage write failed");
+        }
+    });
+}
@@ -0,0 +1,95 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::schema::chat_messages::dsl::{
+    chat_messages, deleted_at, id, message_id as chat_message_id, room as chat_room,
+};
+use crate::schema::chat_moderation_actions::dsl::{
+    action as moderation_action, chat_moderation_actions, expires_at, id as action_id,
+};
+use crate::{
+    errors::chat::ChatHistoryError,
+    models::chat::{
+        ChatMessage, ModerationAction, NewChatMessage, NewModerationAction, ACTION_DELETE,
+    },
+    repositories::{chat::ChatRepository, StorageFuture},
+};
+
+use super::PgStore;
+
+impl ChatRepository for PgStore {
+    fn save(&self, message: NewChatMessage) -> StorageFuture<ChatMessage, ChatHistoryError> {
+        self.run("save_chat_message", move |conn| {
+            diesel::insert_into(chat_messages)
+                .values(&message)
+                .get_result::<ChatMessage>(conn)
+                .map_err(ChatHistoryError::from)
+        })
+    }
+
+    fn history(
+        &self,
+        room: String,
+        before_id: Option<i32>,
+        limit: i64,
+    ) -> StorageFuture<Vec<ChatMessage>, ChatHistoryError> {
+        self.run("chat_history", move |conn| {
+            let mut query = chat_messages
+                .filter(chat_room.eq(room))
+                .filter(deleted_at.is_null())
+                .order(id.desc())
+                .limit(limit)
+                .into_boxed();
+            if let Some(before_id) = before_id {
+                query = query.filter(id.lt(before_id));
+            }
+            query
+                .load::<ChatMessage>(conn)
+                .map_err(ChatHistoryError::from)
+        })
+    }
+
+    fn delete(&self, message_id: String) -> StorageFuture<ChatMessage, ChatHistoryError> {
+        self.run("delete_chat_message", move |conn| {
+            diesel::update(
+                chat_messages
+                    .filter(chat_message_id.eq(message_id))
+                    .filter(deleted_at.is_null()),
+            )
+            .set(deleted_at.eq(Utc::now().naive_utc()))
+            .get_result::<ChatMessage>(conn)
+            .map_err(|e| match e {
+                diesel::result::Error::NotFound => ChatHistoryError::MessageNotFound,
+                e => ChatHistoryError::from(e),
+            })
+        })
+    }
+
+    fn record_moderation(
+        &self,
+        action: NewModerationAction,
+    ) -> StorageFuture<ModerationAction, ChatHistoryError> {
+        self.run("record_moderation_action", move |conn| {
+            diesel::insert_into(chat_moderation_actions)
+                .values(&action)
+                .get_result::<ModerationAction>(conn)
+                .map_err(ChatHistoryError::from)
+        })
+    }
+
+    fn active_moderation(&self) -> StorageFuture<Vec<ModerationAction>, ChatHistoryError> {
+        self.run("active_moderation_actions", move |conn| {
+            //* Deleted messages are already filtered out of the history */
+            chat_moderation_actions
+                .filter(moderation_action.ne(ACTION_DELETE))
+                .filter(
+                    expires_at
+                        .is_null()
+                        .or(expires_at.gt(Utc::now().naive_utc())),
+                )
+                .order(action_id.asc())
+                .load::<ModerationAction>(conn)
+                .map_err(ChatHistoryError::from)
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::ledger_entries::dsl::{created_at, id as ledger_id, ledger_entries};
+use crate::schema::users::dsl::{balance, role, users};
+use crate::{
+    errors::wallet::WalletError,
+    models::{
+        user::ROLE_HOUSE,
+        wallet::{LedgerEntry, NewLedgerEntry},
+    },
+    repositories::{
+        ledger::{ApplyLedgerEntry, LedgerRepository, Transfer},
+        StorageFuture,
+    },
+};
+
+use super::PgStore;
+
+impl LedgerRepository for PgStore {
+    fn apply(&self, entry: ApplyLedgerEntry) -> StorageFuture<LedgerEntry, WalletError> {
+        self.run("apply_ledger_entry", move |conn| {
+            conn.transaction(|conn| {
+                apply_ledger_entry(
+                    conn,
+                    entry.user_id,
+                    entry.amount,
+                    entry.reason,
+                    entry.reference,
+                )
+            })
+        })
+    }
+
+    fn transfer(&self, transfer: Transfer) -> StorageFuture<Vec<LedgerEntry>, WalletError> {
+        self.run("transfer", move |conn| {
+            let total: f64 = transfer.recipients.iter().map(|(_, amount)| amount).sum();
+            let mut entries = vec![(transfer.from_user_id, -total)];
+            entries.extend(transfer.recipients);
+            //* Rows are always locked in id order so concurrent transfers can not deadlock */
+            entries.sort_by_key(|(user_id, _)| *user_id);
+
+            conn.transaction(|conn| {
+                entries
+                    .into_iter()
+                    .map(|(user_id, amount)| {
+                        apply_ledger_entry(
+                            conn,
+                            user_id,
+                            amount,
+                            transfer.reason.clone(),
+                            transfer.reference.clone(),
+                        )
+                    })
+                    .collect()
+            })
+        })
+    }
+
+    fn entries(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        after_id: i32,
+        limit: i64,
+    ) -> StorageFuture<Vec<LedgerEntry>, WalletError> {
+        self.run("ledger_entries", move |conn| {
+            ledger_entries
+                .filter(created_at.ge(from))
+                .filter(created_at.lt(to))
+                .filter(ledger_id.gt(after_id))
+                .order(ledger_id.asc())
+                .limit(limit)
+                .load::<LedgerEntry>(conn)
+                .map_err(WalletError::from)
+        })
+    }
+}
+
+pub(super) fn apply_ledger_entry(
+    conn: &mut PgConnection,
+    user_id: i32,
+    amount: f64,
+    reason: String,
+    reference: Option<String>,
+) -> Result<LedgerEntry, WalletError> {
+    let (current_balance, user_role) = users
+        .find(user_id)
+        .select((balance, role))
+        .for_update()
+        .first::<(f64, String)>(conn)
+        .optional()?
+        .ok_or(WalletError::UserNotFound)?;
+
+    //* The house is the counterparty of every game, so its balance is allowed to go negative */
+    let balance_after = current_balance + amount;
+    if balance_after < 0.0 && user_role != ROLE_HOUSE {
+        return Err(WalletError::InsufficientFunds);
+    }
+
+    diesel::update(users.find(user_id))
+        .set(balance.eq(balance_after))
+        .execute(conn)?;
+
+    diesel::insert_into(ledger_entries)
+        .values(&NewLedgerEntry {
+            user_id,
+            amount,
+            balance_after,
+            reason,
+            reference,
+        })
+        .get_result::<LedgerEntry>(conn)
+        .map_err(WalletError::from)
+}
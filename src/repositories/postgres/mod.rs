@@ -0,0 +1,94 @@
+mod chat;
+mod ledger;
+mod rounds;
+mod users;
+
+use std::sync::Arc;
+
+use actix_web::web;
+use diesel::{prelude::*, sql_query};
+
+use crate::{
+    db_utils::AppDbType,
+    errors::{health::HealthError, storage::StorageError},
+    metrics::metrics,
+};
+
+use super::{Repositories, StorageFuture, StoreHealth};
+
+//* Diesel is blocking, so every query runs on the blocking thread pool and holds a pooled connection only while it runs */
+#[derive(Clone)]
+pub struct PgStore {
+    pool: AppDbType,
+}
+
+impl PgStore {
+    pub fn new(pool: AppDbType) -> Self {
+        PgStore { pool }
+    }
+
+    //* Waiting for a connection is bounded by the pool's connection timeout and surfaces as StorageError::PoolTimeout */
+    fn run<T, E, F>(&self, operation: &'static str, query: F) -> StorageFuture<T, E>
+    where
+        T: Send + 'static,
+        E: From<StorageError> + Send + 'static,
+        F: FnOnce(&mut PgConnection) -> Result<T, E> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+        let waiting = Waiting::new();
+        Box::pin(async move {
+            web::block(move || {
+                let conn = pool.get().map_err(StorageError::from);
+                drop(waiting);
+                let mut conn = conn?;
+                let timer = metrics()
+                    .db_request_duration
+                    .with_label_values(&[operation])
+                    .start_timer();
+                let result = query(&mut conn);
+                timer.observe_duration();
+                result
+            })
+            .await
+            .unwrap_or_else(|_| Err(StorageError::Cancelled.into()))
+        })
+    }
+}
+
+impl StoreHealth for PgStore {
+    fn check(&self) -> StorageFuture<(), HealthError> {
+        self.run("check", |conn| {
+            sql_query("SELECT 1").execute(conn)?;
+            Ok(())
+        })
+    }
+}
+
+impl Repositories {
+    pub fn postgres(pool: AppDbType) -> Self {
+        let store = Arc::new(PgStore::new(pool));
+        Repositories {
+            users: store.clone(),
+            ledger: store.clone(),
+            rounds: store.clone(),
+            chat: store.clone(),
+            health: store,
+        }
+    }
+}
+
+//* Counts a request from the moment it is made until it holds a connection, or is dropped without one */
+struct Waiting;
+
+impl Waiting {
+    fn new() -> Self {
+        metrics().db_pending_requests.inc();
+        Waiting
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        metrics().db_pending_requests.dec();
+    }
+}
@@ -0,0 +1,220 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::schema::game_rounds::dsl::{
+    created_at, game, game_rounds, recovered_at, refunded, round_id, status, updated_at,
+};
+use crate::schema::jackpot_rounds::dsl::{
+    created_at as jackpot_created_at, jackpot_rounds, round_id as jackpot_round_id,
+};
+use crate::schema::ledger_entries::dsl::{
+    amount, ledger_entries, reason, reference, user_id as ledger_user_id,
+};
+use crate::schema::round_bets::dsl::{
+    amount as bet_amount, round_bets, round_id as bet_round_id, user_id as bet_user_id,
+};
+use crate::{
+    errors::{jackpot::RoundHistoryError, round::RoundJournalError},
+    models::{
+        jackpot::{JackpotRound, NewJackpotRound},
+        round::{
+            GameRound, NewGameRound, NewRoundBet, RoundAudit, FINAL_STATUSES, STATUS_OPEN,
+            STATUS_REFUNDED, STATUS_RUNNING, STATUS_SETTLED, STATUS_VOIDED,
+        },
+    },
+    repositories::{
+        rounds::{
+            audit_round, ledger_reasons, open_stakes, JournalBet, JournalRound, RoundRepository,
+        },
+        StorageFuture,
+    },
+};
+
+use super::{ledger::apply_ledger_entry, PgStore};
+
+impl RoundRepository for PgStore {
+    fn journal_round(&self, round: JournalRound) -> StorageFuture<(), RoundJournalError> {
+        self.run("journal_round", move |conn| {
+            //* Journal writes run on several threads, a late update must not reopen a finished round */
+            diesel::insert_into(game_rounds)
+                .values(&NewGameRound {
+                    game: round.game,
+                    round_id: round.round_id.clone(),
+                    status: round.status.clone(),
+                    created_at: Utc::now().naive_utc(),
+                })
+                .on_conflict(round_id)
+                .do_nothing()
+                .execute(conn)?;
+            diesel::update(
+                game_rounds
+                    .filter(round_id.eq(&round.round_id))
+                    .filter(status.ne_all(FINAL_STATUSES)),
+            )
+            .set((
+                status.eq(&round.status),
+                updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    fn journal_bet(&self, bet: JournalBet) -> StorageFuture<(), RoundJournalError> {
+        self.run("journal_bet", move |conn| {
+            diesel::insert_into(round_bets)
+                .values(&NewRoundBet {
+                    round_id: bet.round_id,
+                    user_id: bet.user_id,
+                    amount: bet.amount,
+                })
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    fn recover(
+        &self,
+        game_name: String,
+        started_before: NaiveDateTime,
+    ) -> StorageFuture<Vec<GameRound>, RoundJournalError> {
+        self.run("recover_rounds", move |conn| {
+            conn.transaction(|conn| {
+                let unfinished = game_rounds
+                    .filter(game.eq(&game_name))
+                    .filter(status.eq_any([STATUS_OPEN, STATUS_RUNNING]))
+                    .filter(created_at.lt(started_before))
+                    .for_update()
+                    .load::<GameRound>(conn)?;
+                unfinished
+                    .into_iter()
+                    .map(|round| recover_round(conn, round))
+                    .collect()
+            })
+        })
+    }
+
+    fn recovered(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> StorageFuture<Vec<GameRound>, RoundJournalError> {
+        self.run("recovered_rounds", move |conn| {
+            game_rounds
+                .filter(recovered_at.is_not_null())
+                .order(recovered_at.desc())
+                .limit(limit)
+                .offset(offset)
+                .load::<GameRound>(conn)
+                .map_err(RoundJournalError::from)
+        })
+    }
+
+    fn verify(
+        &self,
+        game_name: String,
+        id: String,
+    ) -> StorageFuture<RoundAudit, RoundJournalError> {
+        self.run("verify_round", move |conn| {
+            let round = game_rounds
+                .filter(game.eq(&game_name))
+                .filter(round_id.eq(&id))
+                .first::<GameRound>(conn)
+                .optional()?
+                .ok_or(RoundJournalError::RoundNotFound)?;
+            let bets = round_bets
+                .filter(bet_round_id.eq(&round.round_id))
+                .select((bet_user_id, bet_amount))
+                .load::<(i32, f64)>(conn)?;
+            let entries = ledger_entries
+                .filter(reference.eq(&round.round_id))
+                .select((ledger_user_id, amount, reason))
+                .load::<(i32, f64, String)>(conn)?;
+            let jackpot = jackpot_rounds
+                .filter(jackpot_round_id.eq(&round.round_id))
+                .first::<JackpotRound>(conn)
+                .optional()?;
+
+            Ok(audit_round(round, bets, entries, jackpot))
+        })
+    }
+
+    fn record_jackpot(
+        &self,
+        round: NewJackpotRound,
+    ) -> StorageFuture<JackpotRound, RoundHistoryError> {
+        self.run("record_jackpot_round", move |conn| {
+            diesel::insert_into(jackpot_rounds)
+                .values(&round)
+                .get_result::<JackpotRound>(conn)
+                .map_err(RoundHistoryError::from)
+        })
+    }
+
+    fn jackpot_history(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> StorageFuture<Vec<JackpotRound>, RoundHistoryError> {
+        self.run("jackpot_history", move |conn| {
+            jackpot_rounds
+                .order(jackpot_created_at.desc())
+                .limit(limit)
+                .offset(offset)
+                .load::<JackpotRound>(conn)
+                .map_err(RoundHistoryError::from)
+        })
+    }
+}
+
+//* The ledger is the source of truth for what was staked, so a round is only refunded what it actually took */
+fn recover_round(
+    conn: &mut PgConnection,
+    round: GameRound,
+) -> Result<GameRound, RoundJournalError> {
+    let mut refund_total = 0.0;
+    let outcome = match ledger_reasons(&round.game) {
+        None => STATUS_VOIDED,
+        Some((bet_reason, payout_reason, refund_reason)) => {
+            let entries = ledger_entries
+                .filter(reference.eq(&round.round_id))
+                .select((ledger_user_id, amount, reason))
+                .load::<(i32, f64, String)>(conn)?;
+
+            if entries.iter().any(|(_, _, r)| r == payout_reason) {
+                STATUS_SETTLED
+            } else {
+                //* Rows are locked in id order, like wallet transfers */
+                for (user, stake) in open_stakes(&entries, bet_reason, refund_reason) {
+                    if stake <= 0.0 {
+                        continue;
+                    }
+                    apply_ledger_entry(
+                        conn,
+                        user,
+                        stake,
+                        refund_reason.to_string(),
+                        Some(round.round_id.clone()),
+                    )?;
+                    refund_total += stake;
+                }
+                if refund_total > 0.0 {
+                    STATUS_REFUNDED
+                } else {
+                    STATUS_VOIDED
+                }
+            }
+        }
+    };
+
+    let now = Utc::now().naive_utc();
+    diesel::update(game_rounds.find(round.id))
+        .set((
+            status.eq(outcome),
+            refunded.eq(refund_total),
+            recovered_at.eq(now),
+            updated_at.eq(now),
+        ))
+        .get_result::<GameRound>(conn)
+        .map_err(RoundJournalError::from)
+}
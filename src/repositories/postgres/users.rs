@@ -0,0 +1,95 @@
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel::sql_types::Text;
+
+use crate::schema::users::dsl::{role, username, users};
+use crate::{
+    errors::{
+        auth::{GetUserError, RegisterError},
+        wallet::WalletError,
+    },
+    models::user::{NewUser, User, NO_PASSWORD, ROLE_HOUSE},
+    repositories::{users::UserRepository, StorageFuture},
+};
+
+use super::PgStore;
+
+define_sql_function! {
+    //* Usernames are unique regardless of case, see users_username_lower_idx */
+    fn lower(x: Text) -> Text;
+}
+
+fn username_available(name: &str, conn: &mut PgConnection) -> bool {
+    match users
+        .filter(lower(username).eq(lower(name)))
+        .first::<User>(conn)
+    {
+        Ok(_) => false, // Username exists
+        Err(Error::NotFound) => true,
+        Err(_) => false, // Other errors, treat as username not available
+    }
+}
+
+impl UserRepository for PgStore {
+    fn create(&self, user: NewUser) -> StorageFuture<User, RegisterError> {
+        self.run("create_user", move |conn| {
+            if !username_available(&user.username, conn) {
+                return Err(RegisterError::UsernameAlreadyRegistered);
+            }
+            //* A concurrent registration of the same name loses on the unique index */
+            diesel::insert_into(users)
+                .values(&user)
+                .get_result::<User>(conn)
+                .map_err(|e| match e {
+                    Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                        RegisterError::UsernameAlreadyRegistered
+                    }
+                    e => RegisterError::DieselError(e),
+                })
+        })
+    }
+
+    fn find(&self, user_id: i32) -> StorageFuture<User, GetUserError> {
+        self.run("find_user", move |conn| {
+            match users.find(user_id).first::<User>(conn) {
+                Ok(user) => Ok(user),
+                Err(Error::NotFound) => Err(GetUserError::UserNotFound),
+                Err(e) => Err(GetUserError::from(e)),
+            }
+        })
+    }
+
+    fn find_by_username(&self, name: String) -> StorageFuture<User, GetUserError> {
+        self.run("find_user_by_username", move |conn| {
+            match users.filter(username.eq(name)).first::<User>(conn) {
+                Ok(user) => Ok(user),
+                Err(Error::NotFound) => Err(GetUserError::UserNotFound),
+                Err(e) => Err(GetUserError::from(e)),
+            }
+        })
+    }
+
+    fn ensure_house(&self, name: String) -> StorageFuture<User, WalletError> {
+        self.run("ensure_house", move |conn| {
+            let new_house = NewUser {
+                username: name.clone(),
+                hashed_password: NO_PASSWORD.to_string(),
+                balance: 0.0,
+                role: ROLE_HOUSE.to_string(),
+            };
+            //* Instances starting together may all insert, the unique index keeps the first one */
+            diesel::insert_into(users)
+                .values(&new_house)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            //* A player holding the name is never used as the house */
+            users
+                .filter(lower(username).eq(lower(name)))
+                .filter(role.eq(ROLE_HOUSE))
+                .first::<User>(conn)
+                .optional()?
+                .ok_or(WalletError::UserNotFound)
+        })
+    }
+}
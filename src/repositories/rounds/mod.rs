@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use tracing::{error, info};
+
+use crate::{
+    errors::{jackpot::RoundHistoryError, round::RoundJournalError},
+    events::{GAME_COINFLIP, GAME_JACKPOT},
+    models::{
+        jackpot::{JackpotRound, NewJackpotRound},
+        round::{GameRound, RoundAudit, STATUS_REFUNDED, STATUS_SETTLED},
+        wallet::{
+            REASON_COINFLIP_BET, REASON_COINFLIP_PAYOUT, REASON_COINFLIP_REFUND,
+            REASON_JACKPOT_BET, REASON_JACKPOT_PAYOUT, REASON_JACKPOT_RAKE, REASON_JACKPOT_REFUND,
+        },
+    },
+    telemetry::round_span,
+};
+
+use super::StorageFuture;
+
+//* Creates the round on first sight, later calls move its status unless it already finished */
+pub struct JournalRound {
+    pub game: String,
+    pub round_id: String,
+    pub status: String,
+}
+
+pub struct JournalBet {
+    pub round_id: String,
+    pub user_id: i32,
+    pub amount: f64,
+}
+
+pub trait RoundRepository: Send + Sync {
+    fn journal_round(&self, round: JournalRound) -> StorageFuture<(), RoundJournalError>;
+    fn journal_bet(&self, bet: JournalBet) -> StorageFuture<(), RoundJournalError>;
+    //* Settles, refunds or voids the unfinished rounds of a game that were opened before `started_before` */
+    fn recover(
+        &self,
+        game: String,
+        started_before: NaiveDateTime,
+    ) -> StorageFuture<Vec<GameRound>, RoundJournalError>;
+    fn recovered(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> StorageFuture<Vec<GameRound>, RoundJournalError>;
+    //* Cross checks a round against the ledger and, for jackpot, the round history */
+    fn verify(
+        &self,
+        game: String,
+        round_id: String,
+    ) -> StorageFuture<RoundAudit, RoundJournalError>;
+    fn record_jackpot(
+        &self,
+        round: NewJackpotRound,
+    ) -> StorageFuture<JackpotRound, RoundHistoryError>;
+    fn jackpot_history(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> StorageFuture<Vec<JackpotRound>, RoundHistoryError>;
+}
+
+//* Startup recovery report, one line per round that was left unfinished */
+pub fn log_recovery(game: &str, result: Result<Vec<GameRound>, RoundJournalError>) {
+    match result {
+        Ok(rounds) => {
+            for round in rounds {
+                info!(
+                    parent: &round_span(game, &round.round_id),
+                    status = %round.status,
+                    refunded = round.refunded,
+                    "Recovered unfinished round"
+                );
+            }
+        }
+        Err(e) => error!(game, error = %e, "Failed to recover rounds"),
+    }
+}
+
+//* (bet, payout, refund) ledger reasons of games that stake through the wallet */
+pub fn ledger_reasons(game_name: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    match game_name {
+        GAME_JACKPOT => Some((
+            REASON_JACKPOT_BET,
+            REASON_JACKPOT_PAYOUT,
+            REASON_JACKPOT_REFUND,
+        )),
+        GAME_COINFLIP => Some((
+            REASON_COINFLIP_BET,
+            REASON_COINFLIP_PAYOUT,
+            REASON_COINFLIP_REFUND,
+        )),
+        _ => None,
+    }
+}
+
+//* What each user still has in a round after bets and refunds, from its (user, amount, reason) ledger entries, in user id order */
+pub fn open_stakes(
+    entries: &[(i32, f64, String)],
+    bet_reason: &str,
+    refund_reason: &str,
+) -> Vec<(i32, f64)> {
+    let mut stakes: HashMap<i32, f64> = HashMap::new();
+    for (user, value, r) in entries {
+        if r == bet_reason || r == refund_reason {
+            *stakes.entry(*user).or_insert(0.0) -= value;
+        }
+    }
+    let mut stakes: Vec<(i32, f64)> = stakes.into_iter().collect();
+    stakes.sort_by_key(|(user, _)| *user);
+    stakes
+}
+
+//* Balances are kept in cents, so anything closer than half a cent is the same amount */
+fn same_amount(a: f64, b: f64) -> bool {
+    (a - b).abs() < 0.005
+}
+
+pub fn audit_round(
+    round: GameRound,
+    bets: Vec<(i32, f64)>,
+    entries: Vec<(i32, f64, String)>,
+    jackpot: Option<JackpotRound>,
+) -> RoundAudit {
+    let mut issues = Vec::new();
+    let journaled_bets = bets.iter().fold(0.0, |sum, (_, value)| sum + value);
+    let (mut staked, mut refund_total, mut paid_out, mut raked) = (0.0, 0.0, 0.0, 0.0);
+
+    //* Crash does not stake through the wallet, its journal has nothing to be checked against */
+    if let Some((bet_reason, payout_reason, refund_reason)) = ledger_reasons(&round.game) {
+        for (_, value, r) in &entries {
+            if r == refund_reason {
+                refund_total += value;
+            } else if r == payout_reason {
+                paid_out += value;
+            } else if r == REASON_JACKPOT_RAKE {
+                raked += value;
+            }
+        }
+        let stakes = open_stakes(&entries, bet_reason, refund_reason);
+        staked = stakes.iter().fold(0.0, |sum, (_, stake)| sum + stake);
+
+        for (user, stake) in stakes {
+            if stake < 0.0 && !same_amount(stake, 0.0) {
+                issues.push(format!("User {user} was refunded more than they staked"));
+            }
+        }
+        if round.status == STATUS_REFUNDED && !same_amount(staked, 0.0) {
+            issues.push(format!("Round is refunded but still holds {staked:.2}"));
+        }
+        if paid_out > 0.0 && round.status != STATUS_SETTLED {
+            issues.push(format!(
+                "Round paid out {paid_out:.2} but is {}",
+                round.status
+            ));
+        }
+    }
+
+    if round.game == GAME_JACKPOT {
+        if round.status == STATUS_SETTLED {
+            if !same_amount(journaled_bets, staked) {
+                issues.push(format!(
+                    "Journaled bets {journaled_bets:.2} do not match ledger stakes {staked:.2}"
+                ));
+            }
+            if paid_out == 0.0 {
+                issues.push("Round is settled but nothing was paid out".to_string());
+            }
+        }
+        match &jackpot {
+            Some(record) if round.status != STATUS_SETTLED => {
+                issues.push(format!(
+                    "Round has a history record but is {}",
+                    round.status
+                ));
+                audit_jackpot_record(record, staked, paid_out, raked, &mut issues);
+            }
+            Some(record) => audit_jackpot_record(record, staked, paid_out, raked, &mut issues),
+            None if round.status == STATUS_SETTLED => {
+                issues.push("Round is settled but has no history record".to_string());
+            }
+            None => (),
+        }
+    }
+
+    RoundAudit {
+        round,
+        journaled_bets,
+        staked,
+        refunded: refund_total,
+        paid_out,
+        raked,
+        jackpot,
+        issues,
+    }
+}
+
+fn audit_jackpot_record(
+    record: &JackpotRound,
+    staked: f64,
+    paid_out: f64,
+    raked: f64,
+    issues: &mut Vec<String>,
+) {
+    if !same_amount(record.pot, staked) {
+        issues.push(format!(
+            "Recorded pot {:.2} does not match ledger stakes {staked:.2}",
+            record.pot
+        ));
+    }
+    if !same_amount(record.payout + record.house_fee, record.pot) {
+        issues.push(format!(
+            "Recorded payout {:.2} and fee {:.2} do not add up to the pot {:.2}",
+            record.payout, record.house_fee, record.pot
+        ));
+    }
+    if !same_amount(record.payout, paid_out) {
+        issues.push(format!(
+            "Recorded payout {:.2} does not match ledger payout {paid_out:.2}",
+            record.payout
+        ));
+    }
+    //* The rake is only booked when a house account exists */
+    if raked > 0.0 && !same_amount(record.house_fee, raked) {
+        issues.push(format!(
+            "Recorded fee {:.2} does not match ledger rake {raked:.2}",
+            record.house_fee
+        ));
+    }
+    if record.winning_ticket < 1 || record.winning_ticket > record.total_tickets {
+        issues.push(format!(
+            "Winning ticket {} is outside 1..={}",
+            record.winning_ticket, record.total_tickets
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::models::round::STATUS_OPEN;
+
+    use super::*;
+
+    fn round(game: &str, status: &str) -> GameRound {
+        let now = Utc::now().naive_utc();
+        GameRound {
+            id: 1,
+            game: game.to_string(),
+            round_id: "round".to_string(),
+            status: status.to_string(),
+            refunded: 0.0,
+            recovered_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    //* Users 1 and 2 each bet twice, the second bet of user 2 was refunded */
+    fn partially_refunded() -> Vec<(i32, f64, String)> {
+        vec![
+            (1, -10.0, REASON_JACKPOT_BET.to_string()),
+            (2, -5.0, REASON_JACKPOT_BET.to_string()),
+            (1, -2.5, REASON_JACKPOT_BET.to_string()),
+            (2, -3.0, REASON_JACKPOT_BET.to_string()),
+            (2, 3.0, REASON_JACKPOT_REFUND.to_string()),
+        ]
+    }
+
+    #[test]
+    fn open_stakes_subtract_refunds() {
+        let mut entries = partially_refunded();
+        entries.push((3, 7.0, REASON_COINFLIP_PAYOUT.to_string()));
+        assert_eq!(
+            open_stakes(&entries, REASON_JACKPOT_BET, REASON_JACKPOT_REFUND),
+            vec![(1, 12.5), (2, 5.0)]
+        );
+    }
+
+    #[test]
+    fn partially_refunded_open_round_is_consistent() {
+        let audit = audit_round(
+            round(GAME_JACKPOT, STATUS_OPEN),
+            vec![(1, 10.0), (2, 5.0), (1, 2.5)],
+            partially_refunded(),
+            None,
+        );
+        assert_eq!(audit.staked, 17.5);
+        assert_eq!(audit.refunded, 3.0);
+        assert_eq!(audit.journaled_bets, 17.5);
+        assert!(audit.issues.is_empty(), "{:?}", audit.issues);
+    }
+
+    #[test]
+    fn refunded_round_must_return_every_stake() {
+        let audit = audit_round(
+            round(GAME_JACKPOT, STATUS_REFUNDED),
+            Vec::new(),
+            partially_refunded(),
+            None,
+        );
+        assert_eq!(
+            audit.issues,
+            vec!["Round is refunded but still holds 17.50".to_string()]
+        );
+
+        let mut entries = partially_refunded();
+        entries.push((1, 12.5, REASON_JACKPOT_REFUND.to_string()));
+        entries.push((2, 6.0, REASON_JACKPOT_REFUND.to_string()));
+        let audit = audit_round(
+            round(GAME_JACKPOT, STATUS_REFUNDED),
+            Vec::new(),
+            entries,
+            None,
+        );
+        assert_eq!(
+            audit.issues,
+            vec![
+                "User 2 was refunded more than they staked".to_string(),
+                "Round is refunded but still holds -1.00".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{
+    errors::{
+        auth::{GetUserError, RegisterError},
+        wallet::WalletError,
+    },
+    models::user::{NewUser, User},
+};
+
+use super::StorageFuture;
+
+pub trait UserRepository: Send + Sync {
+    //* Fails with UsernameAlreadyRegistered instead of inserting a second user with the same name */
+    fn create(&self, user: NewUser) -> StorageFuture<User, RegisterError>;
+    fn find(&self, user_id: i32) -> StorageFuture<User, GetUserError>;
+    fn find_by_username(&self, username: String) -> StorageFuture<User, GetUserError>;
+    //* Returns the house account with the given username, creating it if it does not exist yet */
+    fn ensure_house(&self, username: String) -> StorageFuture<User, WalletError>;
+}
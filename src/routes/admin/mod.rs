@@ -0,0 +1,14 @@
+use actix_web::web::{self, get, ServiceConfig};
+
+use crate::{
+    handlers::admin::handle_recovery::handle_recovery_report,
+    middlewares::only_authorized::OnlyAuthorized,
+};
+
+pub fn init_admin_routes(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::resource("/admin/recovery")
+            .wrap(OnlyAuthorized)
+            .route(get().to(handle_recovery_report)),
+    );
+}
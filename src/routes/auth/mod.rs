@@ -1,8 +1,19 @@
 use actix_web::web::{self, post, ServiceConfig};
 
-use crate::handlers::auth::{handle_login::handle_login, handle_register::handle_register};
+use crate::{
+    handlers::auth::{handle_login::handle_login, handle_register::handle_register},
+    middlewares::rate_limit::IpRateLimit,
+};
 
 pub fn init_auth_routes(cfg: &mut ServiceConfig) {
-    cfg.service(web::resource("/register").route(post().to(handle_register)))
-        .service(web::resource("/login").route(post().to(handle_login)));
+    cfg.service(
+        web::resource("/register")
+            .wrap(IpRateLimit::new(|limiters| &limiters.register))
+            .route(post().to(handle_register)),
+    )
+    .service(
+        web::resource("/login")
+            .wrap(IpRateLimit::new(|limiters| &limiters.login))
+            .route(post().to(handle_login)),
+    );
 }
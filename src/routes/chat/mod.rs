@@ -0,0 +1,7 @@
+use actix_web::web::{self, get, ServiceConfig};
+
+use crate::handlers::chat::handle_history::handle_chat_history;
+
+pub fn init_chat_routes(cfg: &mut ServiceConfig) {
+    cfg.service(web::resource("/chat/history").route(get().to(handle_chat_history)));
+}
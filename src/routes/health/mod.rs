@@ -0,0 +1,8 @@
+use actix_web::web::{self, get, ServiceConfig};
+
+use crate::handlers::health::handle_health::{handle_healthz, handle_readyz};
+
+pub fn init_health_routes(cfg: &mut ServiceConfig) {
+    cfg.service(web::resource("/healthz").route(get().to(handle_healthz)))
+        .service(web::resource("/readyz").route(get().to(handle_readyz)));
+}
@@ -0,0 +1,10 @@
+use actix_web::web::{self, get, ServiceConfig};
+
+use crate::handlers::jackpot::{
+    handle_history::handle_jackpot_history, handle_rooms::handle_jackpot_rooms,
+};
+
+pub fn init_jackpot_routes(cfg: &mut ServiceConfig) {
+    cfg.service(web::resource("/jackpot/history").route(get().to(handle_jackpot_history)))
+        .service(web::resource("/jackpot/rooms").route(get().to(handle_jackpot_rooms)));
+}
@@ -0,0 +1,7 @@
+use actix_web::web::{self, get, ServiceConfig};
+
+use crate::handlers::metrics::handle_metrics::handle_metrics;
+
+pub fn init_metrics_routes(cfg: &mut ServiceConfig) {
+    cfg.service(web::resource("/metrics").route(get().to(handle_metrics)));
+}
@@ -1,11 +1,26 @@
+pub mod admin;
 pub mod auth;
+pub mod chat;
+pub mod health;
+pub mod jackpot;
+pub mod metrics;
 pub mod websockets;
 
 use actix_web::web::ServiceConfig;
+use admin::init_admin_routes;
 use auth::init_auth_routes;
+use chat::init_chat_routes;
+use health::init_health_routes;
+use jackpot::init_jackpot_routes;
+use metrics::init_metrics_routes;
 use websockets::init_websocket_routes;
 
 pub fn init_routes(cfg: &mut ServiceConfig) {
-    cfg.configure(init_auth_routes)
+    cfg.configure(init_admin_routes)
+        .configure(init_auth_routes)
+        .configure(init_chat_routes)
+        .configure(init_health_routes)
+        .configure(init_jackpot_routes)
+        .configure(init_metrics_routes)
         .configure(init_websocket_routes);
 }
@@ -11,6 +11,74 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    chat_messages (id) {
+        id -> Int4,
+        #[max_length = 36]
+        message_id -> Varchar,
+        user_id -> Int4,
+        #[max_length = 255]
+        username -> Varchar,
+        message -> Text,
+        created_at -> Timestamp,
+        #[max_length = 100]
+        room -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    chat_moderation_actions (id) {
+        id -> Int4,
+        #[max_length = 20]
+        action -> Varchar,
+        moderator_id -> Int4,
+        target_user_id -> Nullable<Int4>,
+        #[max_length = 100]
+        room -> Nullable<Varchar>,
+        #[max_length = 36]
+        message_id -> Nullable<Varchar>,
+        duration_secs -> Nullable<Int8>,
+        reason -> Nullable<Text>,
+        expires_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    game_rounds (id) {
+        id -> Int4,
+        #[max_length = 20]
+        game -> Varchar,
+        #[max_length = 36]
+        round_id -> Varchar,
+        #[max_length = 20]
+        status -> Varchar,
+        refunded -> Float8,
+        recovered_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    jackpot_rounds (id) {
+        id -> Int4,
+        #[max_length = 36]
+        round_id -> Varchar,
+        winner_id -> Int4,
+        #[max_length = 255]
+        winner_name -> Varchar,
+        pot -> Float8,
+        house_fee -> Float8,
+        payout -> Float8,
+        winning_ticket -> Int8,
+        total_tickets -> Int8,
+        player_count -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     jackpotplayers (player_id) {
         player_id -> Int4,
@@ -19,6 +87,31 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    ledger_entries (id) {
+        id -> Int4,
+        user_id -> Int4,
+        amount -> Float8,
+        balance_after -> Float8,
+        #[max_length = 50]
+        reason -> Varchar,
+        #[max_length = 255]
+        reference -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    round_bets (id) {
+        id -> Int4,
+        #[max_length = 36]
+        round_id -> Varchar,
+        user_id -> Int4,
+        amount -> Float8,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -26,11 +119,16 @@ diesel::table! {
         username -> Varchar,
         #[max_length = 255]
         hashed_password -> Varchar,
-        balance -> Int4,
+        balance -> Float8,
         created_at -> Timestamp,
+        #[max_length = 20]
+        role -> Varchar,
     }
 }
 
+diesel::joinable!(chat_messages -> users (user_id));
+diesel::joinable!(jackpot_rounds -> users (winner_id));
 diesel::joinable!(jackpotplayers -> users (player_id));
+diesel::joinable!(ledger_entries -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(jackpotgames, jackpotplayers, users,);
+diesel::allow_tables_to_appear_in_same_query!(jackpotgames, jackpotplayers, ledger_entries, users,);
@@ -0,0 +1,150 @@
+use std::{io, net::TcpListener, sync::Arc};
+
+use actix::{Actor, Addr};
+use actix_cors::Cors;
+use actix_web::{dev::Server, http::header, web::Data, App, HttpServer};
+use tracing::warn;
+
+use crate::{
+    config::Settings,
+    db_utils::AppState,
+    events::EventBus,
+    handlers::websocket::{
+        chat::chat_server::{ChatServer, MessageFilter},
+        coinflip::{coinflip_server::CoinflipServer, house_bot::HouseBotConfig},
+        crash::crash_server::CrashServer,
+        jackpot::jackpot_server::JackpotServer,
+    },
+    health::ReadinessProbe,
+    middlewares::request_span::RequestSpan,
+    rate_limit::RateLimiters,
+    repositories::Repositories,
+    routes::init_routes,
+    shutdown::ShutdownCoordinator,
+};
+
+#[cfg(any(test, feature = "test-hooks"))]
+use crate::handlers::websocket::crash::crash_server::CrashPoints;
+
+//* The game actors behind the routes, started once and shared by every worker */
+#[derive(Clone)]
+pub struct GameServers {
+    pub events: Addr<EventBus>,
+    pub chat: Addr<ChatServer>,
+    pub message_filter: MessageFilter,
+    pub jackpot: Addr<JackpotServer>,
+    pub coinflip: Addr<CoinflipServer>,
+    pub crash: Addr<CrashServer>,
+}
+
+impl GameServers {
+    pub async fn start(settings: &Settings, repos: &Repositories) -> Self {
+        let crash = CrashServer::new(repos.clone(), settings.crash);
+        Self::launch(settings, repos, crash).await
+    }
+
+    //* Crash points come from `crash_points` instead of the round seeds, so tests can script rounds */
+    #[cfg(any(test, feature = "test-hooks"))]
+    pub async fn start_with_crash_points(
+        settings: &Settings,
+        repos: &Repositories,
+        crash_points: CrashPoints,
+    ) -> Self {
+        let crash = CrashServer::new(repos.clone(), settings.crash).with_crash_points(crash_points);
+        Self::launch(settings, repos, crash).await
+    }
+
+    async fn launch(settings: &Settings, repos: &Repositories, crash: CrashServer) -> Self {
+        //* Resolved once for every game, so the house is never created twice */
+        let house = repos
+            .users
+            .ensure_house(settings.house.account_username.clone())
+            .await
+            .inspect_err(
+                |e| warn!(error = %e, "Jackpot rake disabled: could not load house account"),
+            )
+            .ok();
+        let events = EventBus::new().start();
+        let chat_settings = Arc::new(settings.chat.clone());
+        let message_filter = MessageFilter::new(chat_settings.clone());
+        let chat = ChatServer::new(repos.clone(), chat_settings, events.clone()).start();
+        let jackpot = JackpotServer::new(
+            repos.clone(),
+            settings.jackpot.clone(),
+            events.clone(),
+            house.as_ref().map(|house| house.id),
+        )
+        .start();
+        let crash = crash.start();
+        let house_bot = HouseBotConfig::from_settings(&settings.coinflip, house.as_ref());
+        let coinflip = CoinflipServer::new(repos.clone(), house_bot, events.clone()).start();
+        GameServers {
+            events,
+            chat,
+            message_filter,
+            jackpot,
+            coinflip,
+            crash,
+        }
+    }
+}
+
+//* The returned server runs until a shutdown signal, after the games had the chance to settle */
+pub fn serve(
+    settings: Settings,
+    repos: Repositories,
+    servers: GameServers,
+    listener: TcpListener,
+) -> io::Result<Server> {
+    let rate_limiters = Data::new(RateLimiters::from_settings(&settings.rate_limit));
+    let mut shutdown = ShutdownCoordinator::new(settings.shutdown.timeout());
+    shutdown.add("jackpot", servers.jackpot.clone().recipient());
+    shutdown.add("coinflip", servers.coinflip.clone().recipient());
+    shutdown.add("chat", servers.chat.clone().recipient());
+    shutdown.add("crash", servers.crash.clone().recipient());
+    let mut readiness =
+        ReadinessProbe::new(repos.health.clone(), settings.health.readiness_timeout());
+    readiness.add("jackpot", servers.jackpot.clone().recipient());
+    readiness.add("coinflip", servers.coinflip.clone().recipient());
+    readiness.add("chat", servers.chat.clone().recipient());
+    readiness.add("crash", servers.crash.clone().recipient());
+    let readiness = Data::new(readiness);
+    let server = HttpServer::new(move || {
+        let servers = servers.clone();
+        App::new()
+            .wrap(
+                settings
+                    .server
+                    .cors_origins
+                    .iter()
+                    .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+                    .allowed_headers(vec![
+                        header::AUTHORIZATION,
+                        header::ACCEPT,
+                        header::CONTENT_TYPE,
+                    ])
+                    .supports_credentials()
+                    .max_age(3600),
+            )
+            .wrap(RequestSpan)
+            .app_data(Data::new(servers.jackpot.clone()))
+            .app_data(Data::new(servers.chat.clone()))
+            .app_data(Data::new(servers.message_filter.clone()))
+            .app_data(rate_limiters.clone())
+            .app_data(readiness.clone())
+            .app_data(Data::new(settings.clone()))
+            .app_data(Data::new(servers.coinflip.clone()))
+            .app_data(Data::new(servers.crash.clone()))
+            .app_data(Data::new(AppState {
+                repos: repos.clone(),
+            }))
+            .configure(init_routes)
+    })
+    //* Signals are handled by the shutdown coordinator so rounds are settled before the server stops */
+    .disable_signals()
+    .listen(listener)?
+    .run();
+    actix_web::rt::spawn(shutdown.run(server.handle()));
+    Ok(server)
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use actix::{Message, Recipient};
+use actix_web::{dev::ServerHandle, rt};
+use futures::future::{self, join_all};
+use tracing::{info, warn};
+
+pub const SHUTDOWN_NOTICE: &str =
+    "The server is restarting, open rounds have been settled or refunded";
+
+//* Servers stop taking bets and settle or refund their open rounds before replying */
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Shutdown;
+
+pub struct ShutdownCoordinator {
+    servers: Vec<(&'static str, Recipient<Shutdown>)>,
+    //* How long each server gets to settle before the process stops anyway */
+    timeout: Duration,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(timeout: Duration) -> Self {
+        ShutdownCoordinator {
+            servers: Vec::new(),
+            timeout,
+        }
+    }
+
+    pub fn add(&mut self, name: &'static str, server: Recipient<Shutdown>) {
+        self.servers.push((name, server));
+    }
+
+    //* Waits for SIGINT or SIGTERM, settles every server, then stops the HTTP server */
+    pub async fn run(self, http: ServerHandle) {
+        wait_for_signal().await;
+        info!("Shutting down, settling open rounds");
+
+        let timeout = self.timeout;
+        join_all(self.servers.into_iter().map(|(name, server)| async move {
+            match rt::time::timeout(timeout, server.send(Shutdown)).await {
+                Ok(Ok(())) => info!(server = name, "Server settled"),
+                Ok(Err(_)) => warn!(server = name, "Server stopped before it could settle"),
+                Err(_) => warn!(server = name, ?timeout, "Server did not settle in time"),
+            }
+        }))
+        .await;
+
+        http.stop(true).await;
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use rt::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            let interrupt = Box::pin(rt::signal::ctrl_c());
+            let terminate = Box::pin(terminate.recv());
+            future::select(interrupt, terminate).await;
+        }
+        Err(_) => {
+            let _ = rt::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = rt::signal::ctrl_c().await;
+}
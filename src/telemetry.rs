@@ -0,0 +1,50 @@
+use std::io::{self, IsTerminal};
+
+use tracing::{info_span, Span};
+use tracing_subscriber::{fmt::MakeWriter, EnvFilter};
+use uuid::Uuid;
+
+use crate::config::{LogFormat, LogSettings};
+
+//* Installs the global subscriber, JSON lines carry the fields of every enclosing span */
+pub fn init(settings: &LogSettings) {
+    install(settings, io::stdout, io::stdout().is_terminal());
+}
+
+//* For admin commands, whose own output goes to stdout */
+pub fn init_stderr(settings: &LogSettings) {
+    install(settings, io::stderr, io::stderr().is_terminal());
+}
+
+fn install<W>(settings: &LogSettings, writer: W, ansi: bool)
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let filter = EnvFilter::try_new(&settings.level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match settings.format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init(),
+    }
+}
+
+//* One span per websocket, kept by the connection actor for its whole life */
+pub fn connection_span(channel: &'static str, user_id: i32) -> Span {
+    info_span!(
+        "ws_connection",
+        channel,
+        user_id,
+        connection_id = %Uuid::new_v4()
+    )
+}
+
+pub fn round_span(game: &str, round_id: &str) -> Span {
+    info_span!("round", game, round_id)
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use awc::{
+    error::WsClientError,
+    http::{header::AUTHORIZATION, StatusCode},
+    Client,
+};
+use common::TestApp;
+use serde_json::{json, Value};
+
+#[actix_web::test]
+async fn register_and_login() {
+    let app = TestApp::spawn().await;
+    let client = Client::new();
+    let credentials = json!({"username": "alice", "password": "correct horse"});
+
+    let response = client
+        .post(app.url("/register"))
+        .send_json(&credentials)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let mut response = client
+        .post(app.url("/register"))
+        .send_json(&credentials)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["variant"], "FieldTaken");
+
+    let response = client
+        .post(app.url("/register"))
+        .send_json(&json!({"username": "<bob>", "password": "correct horse"}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    for wrong in [
+        json!({"username": "alice", "password": "wrong"}),
+        json!({"username": "nobody", "password": "correct horse"}),
+    ] {
+        let response = client
+            .post(app.url("/login"))
+            .send_json(&wrong)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let mut response = client
+        .post(app.url("/login"))
+        .send_json(&credentials)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    let token = body["token"].as_str().unwrap();
+    app.ws(&format!("/ws/jackpot?token={token}")).await;
+}
+
+#[actix_web::test]
+async fn house_account_name_is_reserved() {
+    let app = TestApp::spawn().await;
+
+    let mut response = Client::new()
+        .post(app.url("/register"))
+        .send_json(&json!({"username": "House", "password": "correct horse"}))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["variant"], "FieldTaken");
+}
+
+#[actix_web::test]
+async fn websockets_reject_invalid_tokens() {
+    let app = TestApp::spawn().await;
+
+    for path in [
+        "/ws/chat?token=garbage",
+        "/ws/jackpot?token=garbage",
+        "/ws/coinflip?token=garbage",
+    ] {
+        let rejected = app.try_ws(path).await.err().expect("Token was accepted");
+        assert!(
+            matches!(
+                rejected,
+                WsClientError::InvalidResponseStatus(StatusCode::UNAUTHORIZED)
+            ),
+            "{path}: {rejected:?}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn admin_routes_need_an_admin() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 0.0).await;
+    let client = Client::new();
+
+    let response = client.get(app.url("/admin/recovery")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .get(app.url("/admin/recovery"))
+        .insert_header((AUTHORIZATION, alice.token.as_str()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
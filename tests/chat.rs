@@ -0,0 +1,103 @@
+mod common;
+
+use common::{eventually, TestApp};
+use serde_json::json;
+
+#[actix_web::test]
+async fn messages_are_broadcast_to_the_room() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 0.0).await;
+    let bob = app.player("bob", 0.0).await;
+    let mut alice_ws = app.ws(&format!("/ws/chat?token={}", alice.token)).await;
+    let mut bob_ws = app.ws(&format!("/ws/chat?token={}", bob.token)).await;
+
+    alice_ws
+        .send(json!({"action": "message", "msg": "hello everyone"}))
+        .await;
+    let received = bob_ws.expect(|m| m["msg"] == "hello everyone").await;
+    assert_eq!(received["username"], "alice");
+    assert_eq!(received["id"], alice.user.id);
+    assert_eq!(received["room"], "global");
+    let echoed = alice_ws.expect(|m| m["msg"] == "hello everyone").await;
+    assert_eq!(echoed["message_id"], received["message_id"]);
+
+    bob_ws
+        .send(json!({"action": "message", "msg": "hi alice"}))
+        .await;
+    alice_ws.expect(|m| m["msg"] == "hi alice").await;
+
+    eventually(|| async {
+        app.repos
+            .chat
+            .history("global".to_string(), None, 10)
+            .await
+            .is_ok_and(|history| history.len() == 2)
+    })
+    .await;
+}
+
+#[actix_web::test]
+async fn anonymous_sockets_cannot_moderate() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 0.0).await;
+    let mut anonymous = app.ws("/ws/chat").await;
+
+    anonymous
+        .send(json!({"action": "mute", "user_id": alice.user.id, "duration_secs": 60}))
+        .await;
+    let rejected = anonymous
+        .expect(|m| m["variant"] == "moderation_rejected")
+        .await;
+    assert_eq!(rejected["payload"]["reason"], "Not allowed");
+}
+
+#[actix_web::test]
+async fn closing_one_tab_keeps_the_other_in_the_room() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 0.0).await;
+    let bob = app.player("bob", 0.0).await;
+    let mut first_tab = app.ws(&format!("/ws/chat?token={}", alice.token)).await;
+    let mut second_tab = app.ws(&format!("/ws/chat?token={}", alice.token)).await;
+    let mut bob_ws = app.ws(&format!("/ws/chat?token={}", bob.token)).await;
+
+    let joined = |m: &serde_json::Value| m["variant"] == "members" && m["room"] == "jackpot";
+    first_tab
+        .send(json!({"action": "join", "room": "jackpot"}))
+        .await;
+    first_tab.expect(joined).await;
+    bob_ws
+        .send(json!({"action": "join", "room": "jackpot"}))
+        .await;
+    bob_ws.expect(joined).await;
+
+    drop(first_tab);
+    bob_ws
+        .expect(|m| {
+            m["variant"] == "member_left"
+                && m["room"] == "jackpot"
+                && m["payload"]["id"] == alice.user.id
+        })
+        .await;
+
+    bob_ws
+        .send(json!({"action": "message", "msg": "still there?"}))
+        .await;
+    second_tab.expect(|m| m["msg"] == "still there?").await;
+
+    second_tab
+        .send(json!({"action": "members", "room": "global"}))
+        .await;
+    let members = second_tab
+        .expect(|m| {
+            m["variant"] == "members"
+                && m["payload"]["members"]
+                    .as_array()
+                    .is_some_and(|members| members.iter().any(|m| m["id"] == bob.user.id))
+        })
+        .await;
+    let listed = members["payload"]["members"].as_array().unwrap();
+    assert_eq!(
+        listed.iter().filter(|m| m["id"] == alice.user.id).count(),
+        1
+    );
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::{eventually, TestApp};
+use jackpot_rust::{
+    events::GAME_COINFLIP,
+    models::round::{STATUS_REFUNDED, STATUS_SETTLED},
+    shutdown::Shutdown,
+};
+use serde_json::json;
+
+const WON: &str = "You won $5.00!";
+const LOST: &str = "You lost. Better luck next time!";
+
+#[actix_web::test]
+async fn create_join_and_settle() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 20.0).await;
+    let bob = app.player("bob", 20.0).await;
+    let mut creator = app.ws(&format!("/ws/coinflip?token={}", alice.token)).await;
+    let mut joiner = app.ws(&format!("/ws/coinflip?token={}", bob.token)).await;
+
+    creator
+        .send(json!({"msg_type": "create", "payload": {"amount": 5}}))
+        .await;
+    let new_game = creator.expect(|m| m["message_type"] == "new_game").await;
+    assert_eq!(new_game["payload"]["amount"], 5.0);
+    assert_eq!(new_game["payload"]["player"]["id"], alice.user.id);
+    let game_id = new_game["payload"]["game_id"].as_str().unwrap().to_string();
+    assert_eq!(app.balance(alice.user.id).await, 15.0);
+
+    joiner
+        .send(json!({"msg_type": "join", "payload": {"game_id": game_id}}))
+        .await;
+    let is_result = |text: &str| text == WON || text == LOST;
+    let creator_result = creator.expect_text(is_result).await;
+    let joiner_result = joiner.expect_text(is_result).await;
+    assert_ne!(creator_result, joiner_result);
+
+    let (winner, loser) = if creator_result == WON {
+        (&alice, &bob)
+    } else {
+        (&bob, &alice)
+    };
+    eventually(|| async { app.balance(winner.user.id).await == 25.0 }).await;
+    assert_eq!(app.balance(loser.user.id).await, 15.0);
+
+    eventually(|| async {
+        app.repos
+            .rounds
+            .verify(GAME_COINFLIP.to_string(), game_id.clone())
+            .await
+            .is_ok_and(|audit| {
+                audit.round.status == STATUS_SETTLED
+                    && audit.journaled_bets == 10.0
+                    && audit.paid_out == 10.0
+                    && audit.issues.is_empty()
+            })
+    })
+    .await;
+}
+
+#[actix_web::test]
+async fn joining_needs_the_stake() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 20.0).await;
+    let bob = app.player("bob", 2.0).await;
+    let mut creator = app.ws(&format!("/ws/coinflip?token={}", alice.token)).await;
+    let mut joiner = app.ws(&format!("/ws/coinflip?token={}", bob.token)).await;
+
+    creator
+        .send(json!({"msg_type": "create", "payload": {"amount": 50}}))
+        .await;
+    let rejected = creator
+        .expect(|m| m["message_type"] == "create_rejected")
+        .await;
+    assert_eq!(rejected["payload"]["msg"], "Insufficient funds");
+
+    creator
+        .send(json!({"msg_type": "create", "payload": {"amount": 5}}))
+        .await;
+    let new_game = creator.expect(|m| m["message_type"] == "new_game").await;
+    let game_id = new_game["payload"]["game_id"].as_str().unwrap();
+
+    joiner
+        .send(json!({"msg_type": "join", "payload": {"game_id": game_id}}))
+        .await;
+    let rejected = joiner
+        .expect(|m| m["message_type"] == "join_rejected")
+        .await;
+    assert_eq!(rejected["payload"]["msg"], "Insufficient funds");
+    assert_eq!(app.balance(alice.user.id).await, 15.0);
+    assert_eq!(app.balance(bob.user.id).await, 2.0);
+}
+
+#[actix_web::test]
+async fn shutdown_refunds_open_games() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 20.0).await;
+    let mut creator = app.ws(&format!("/ws/coinflip?token={}", alice.token)).await;
+
+    creator
+        .send(json!({"msg_type": "create", "payload": {"amount": 5}}))
+        .await;
+    let new_game = creator.expect(|m| m["message_type"] == "new_game").await;
+    let game_id = new_game["payload"]["game_id"].as_str().unwrap().to_string();
+
+    app.servers.coinflip.send(Shutdown).await.unwrap();
+    creator
+        .expect(|m| m["message_type"] == "game_cancelled")
+        .await;
+    assert_eq!(app.balance(alice.user.id).await, 20.0);
+    eventually(|| async {
+        app.repos
+            .rounds
+            .verify(GAME_COINFLIP.to_string(), game_id.clone())
+            .await
+            .is_ok_and(|audit| audit.round.status == STATUS_REFUNDED)
+    })
+    .await;
+}
+
+#[actix_web::test]
+async fn spectators_can_not_play() {
+    let app = TestApp::spawn().await;
+    let mut spectator = app.ws("/ws/coinflip").await;
+
+    spectator
+        .send(json!({"msg_type": "create", "payload": {"user_id": 1, "amount": 5}}))
+        .await;
+    spectator
+        .expect(|m| m["message_type"] == "create_rejected")
+        .await;
+}
@@ -0,0 +1,202 @@
+#![allow(dead_code)]
+
+use std::{
+    env,
+    future::Future,
+    net::TcpListener,
+    sync::Once,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use actix_codec::Framed;
+use actix_web::rt::{self, time};
+use awc::{error::WsClientError, ws, BoxedSocket, Client};
+use futures::{SinkExt, StreamExt};
+use jackpot_rust::{
+    config::Settings,
+    handlers::websocket::crash::crash_server::CrashPoints,
+    jwt::generate_jwt,
+    models::{
+        user::{NewUser, User, ROLE_USER},
+        wallet::REASON_ADMIN_ADJUSTMENT,
+    },
+    repositories::{ledger::ApplyLedgerEntry, Repositories},
+    server::{self, GameServers},
+};
+use serde_json::Value;
+
+//* How long a scenario waits for a message or a side effect before failing */
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+static ENV: Once = Once::new();
+
+//* The whole app on a random local port, backed by the in-memory store */
+pub struct TestApp {
+    pub address: String,
+    pub repos: Repositories,
+    pub servers: GameServers,
+}
+
+pub struct Player {
+    pub user: User,
+    pub token: String,
+}
+
+impl TestApp {
+    pub async fn spawn() -> Self {
+        Self::start(None).await
+    }
+
+    //* Crash rounds end at the points drawn from `crash_points` */
+    pub async fn spawn_with_crash(crash_points: CrashPoints) -> Self {
+        Self::start(Some(crash_points)).await
+    }
+
+    async fn start(crash_points: Option<CrashPoints>) -> Self {
+        ENV.call_once(|| env::set_var("JWT_SECRET_KEY", "integration-tests"));
+
+        let mut settings = Settings::default();
+        settings.jackpot.countdown_secs = 1;
+        settings.crash.tick_ms = 20;
+        settings.crash.cooldown_secs = 1;
+
+        let repos = Repositories::memory();
+        let servers = match crash_points {
+            Some(crash_points) => {
+                GameServers::start_with_crash_points(&settings, &repos, crash_points).await
+            }
+            None => GameServers::start(&settings, &repos).await,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind a local port");
+        let address = listener.local_addr().unwrap().to_string();
+        let server = server::serve(settings, repos.clone(), servers.clone(), listener)
+            .expect("Failed to start the server");
+        rt::spawn(server);
+
+        TestApp {
+            address,
+            repos,
+            servers,
+        }
+    }
+
+    //* A user with `balance` credited through the ledger and a valid token */
+    pub async fn player(&self, username: &str, balance: f64) -> Player {
+        let user = self
+            .repos
+            .users
+            .create(NewUser {
+                username: username.to_string(),
+                hashed_password: String::new(),
+                balance: 0.0,
+                role: ROLE_USER.to_string(),
+            })
+            .await
+            .expect("Failed to create user");
+        if balance != 0.0 {
+            self.repos
+                .ledger
+                .apply(ApplyLedgerEntry {
+                    user_id: user.id,
+                    amount: balance,
+                    reason: REASON_ADMIN_ADJUSTMENT.to_string(),
+                    reference: None,
+                })
+                .await
+                .expect("Failed to credit user");
+        }
+        let exp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_secs(3600);
+        let token = generate_jwt(username, user.id, exp.as_secs() as usize).unwrap();
+        Player { user, token }
+    }
+
+    pub async fn balance(&self, user_id: i32) -> f64 {
+        self.repos.users.find(user_id).await.unwrap().balance
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    pub async fn ws(&self, path: &str) -> WsClient {
+        self.try_ws(path).await.expect("Failed to open websocket")
+    }
+
+    pub async fn try_ws(&self, path: &str) -> Result<WsClient, WsClientError> {
+        let (_, socket) = Client::new()
+            .ws(format!("ws://{}{}", self.address, path))
+            .connect()
+            .await?;
+        Ok(WsClient { socket })
+    }
+}
+
+pub struct WsClient {
+    socket: Framed<BoxedSocket, ws::Codec>,
+}
+
+impl WsClient {
+    pub async fn send(&mut self, message: Value) {
+        self.socket
+            .send(ws::Message::Text(message.to_string().into()))
+            .await
+            .expect("Failed to send");
+    }
+
+    //* The next text frame, pings are answered on the way */
+    pub async fn next_text(&mut self) -> String {
+        loop {
+            let frame = time::timeout(TIMEOUT, self.socket.next())
+                .await
+                .expect("Timed out waiting for a message")
+                .expect("Websocket closed")
+                .expect("Websocket protocol error");
+            match frame {
+                ws::Frame::Text(bytes) => return String::from_utf8(bytes.to_vec()).unwrap(),
+                ws::Frame::Ping(bytes) => {
+                    self.socket.send(ws::Message::Pong(bytes)).await.unwrap();
+                }
+                ws::Frame::Close(reason) => panic!("Websocket closed: {reason:?}"),
+                _ => {}
+            }
+        }
+    }
+
+    //* Skips text frames until one `matches` */
+    pub async fn expect_text(&mut self, matches: impl Fn(&str) -> bool) -> String {
+        time::timeout(TIMEOUT, async {
+            loop {
+                let text = self.next_text().await;
+                if matches(&text) {
+                    return text;
+                }
+            }
+        })
+        .await
+        .expect("Timed out waiting for the expected message")
+    }
+
+    //* Like expect_text, for JSON messages; plain-text notices are skipped */
+    pub async fn expect(&mut self, matches: impl Fn(&Value) -> bool) -> Value {
+        let text = self
+            .expect_text(|text| serde_json::from_str(text).is_ok_and(|json| matches(&json)))
+            .await;
+        serde_json::from_str(&text).unwrap()
+    }
+}
+
+//* Polls `check` until it holds, for effects the actors apply in the background */
+pub async fn eventually<F, Fut>(check: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let waited = time::timeout(TIMEOUT, async {
+        while !check().await {
+            time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await;
+    assert!(waited.is_ok(), "Condition never held");
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::TestApp;
+use serde_json::json;
+
+#[actix_web::test]
+async fn deposit_cash_out_and_crash() {
+    let app = TestApp::spawn_with_crash(Box::new(|| 1.02)).await;
+    let mut ws = app.ws("/ws/crash").await;
+
+    //* Bets are only taken between rounds, so wait for the first one to end */
+    ws.expect(|m| m["msg_type"] == "crash").await;
+    ws.send(json!({"action": "deposit", "amount": 5.0})).await;
+    ws.expect(|m| m["msg_type"] == "success_deposit").await;
+
+    ws.expect(|m| m["msg_type"] == "start").await;
+    ws.send(json!({"action": "deposit", "amount": 5.0})).await;
+    ws.expect(|m| m["msg_type"] == "failed_to_deposit").await;
+    ws.send(json!({"action": "cashout"})).await;
+    ws.expect(|m| m["msg_type"] == "success_cashout").await;
+
+    let crash = ws.expect(|m| m["msg_type"] == "crash").await;
+    assert!(crash["msg"].as_str().unwrap().contains("1.02"), "{crash}");
+}
+
+#[actix_web::test]
+async fn cash_out_without_a_bet_fails() {
+    let app = TestApp::spawn_with_crash(Box::new(|| 1.02)).await;
+    let mut ws = app.ws("/ws/crash").await;
+
+    ws.expect(|m| m["msg_type"] == "crash").await;
+    ws.expect(|m| m["msg_type"] == "start").await;
+    ws.send(json!({"action": "cashout"})).await;
+    ws.expect(|m| m["msg_type"] == "failed_to_cashout").await;
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::{eventually, TestApp};
+use jackpot_rust::events::GAME_JACKPOT;
+use serde_json::json;
+
+#[actix_web::test]
+async fn two_players_deposit_and_one_wins_the_pot() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 10.0).await;
+    let bob = app.player("bob", 10.0).await;
+    let mut alice_ws = app.ws(&format!("/ws/jackpot?token={}", alice.token)).await;
+    let mut bob_ws = app.ws(&format!("/ws/jackpot?token={}", bob.token)).await;
+
+    alice_ws
+        .send(json!({"action": "deposit", "amount": 2.0}))
+        .await;
+    bob_ws
+        .expect(|m| m["variant"] == "player_join" && m["payload"]["user_id"] == alice.user.id)
+        .await;
+    bob_ws
+        .send(json!({"action": "deposit", "amount": 3.0}))
+        .await;
+    alice_ws.expect(|m| m["variant"] == "timer_start").await;
+
+    let winner = alice_ws.expect(|m| m["variant"] == "winner").await;
+    let seen_by_bob = bob_ws.expect(|m| m["variant"] == "winner").await;
+    assert_eq!(winner["payload"], seen_by_bob["payload"]);
+
+    let payload = &winner["payload"];
+    assert_eq!(payload["pot_total"], 5.0);
+    assert_eq!(payload["house_fee"], 0.25);
+    assert_eq!(payload["payout"], 4.75);
+    let winner_id = payload["winner"]["user_id"].as_i64().unwrap() as i32;
+    assert!(winner_id == alice.user.id || winner_id == bob.user.id);
+
+    let (alice_after, bob_after) = if winner_id == alice.user.id {
+        (8.0 + 4.75, 7.0)
+    } else {
+        (8.0, 7.0 + 4.75)
+    };
+    eventually(|| async {
+        app.balance(alice.user.id).await == alice_after
+            && app.balance(bob.user.id).await == bob_after
+    })
+    .await;
+
+    let round_id = payload["round_id"].as_str().unwrap().to_string();
+    eventually(|| async {
+        app.repos
+            .rounds
+            .verify(GAME_JACKPOT.to_string(), round_id.clone())
+            .await
+            .is_ok_and(|audit| audit.issues.is_empty())
+    })
+    .await;
+}
+
+#[actix_web::test]
+async fn deposits_beyond_the_balance_are_rejected() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 1.0).await;
+    let mut ws = app.ws(&format!("/ws/jackpot?token={}", alice.token)).await;
+
+    ws.send(json!({"action": "deposit", "amount": 5.0})).await;
+    ws.expect(|m| m["variant"] == "deposit_rejected").await;
+    assert_eq!(app.balance(alice.user.id).await, 1.0);
+}
+
+#[actix_web::test]
+async fn deposits_go_to_the_room_of_the_socket() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 50.0).await;
+    let mut medium = app
+        .ws(&format!("/ws/jackpot?token={}&room=medium", alice.token))
+        .await;
+    let low = app.ws(&format!("/ws/jackpot?token={}", alice.token)).await;
+
+    medium
+        .send(json!({"action": "deposit", "amount": 20.0}))
+        .await;
+    let joined = medium.expect(|m| m["variant"] == "player_join").await;
+    assert_eq!(joined["payload"]["room"], "medium");
+
+    drop(low);
+    medium
+        .send(json!({"action": "deposit", "amount": 5.0}))
+        .await;
+    medium
+        .expect(|m| m["variant"] == "deposit_rejected" && m["msg"] == "Deposit must be at least 10")
+        .await;
+    assert_eq!(app.balance(alice.user.id).await, 30.0);
+}
+
+#[actix_web::test]
+async fn deposits_follow_a_room_switch_right_away() {
+    let app = TestApp::spawn().await;
+    let alice = app.player("alice", 50.0).await;
+    let mut ws = app.ws(&format!("/ws/jackpot?token={}", alice.token)).await;
+
+    ws.send(json!({"action": "join_room", "room": "medium"}))
+        .await;
+    ws.send(json!({"action": "deposit", "amount": 20.0})).await;
+    let joined = ws.expect(|m| m["variant"] == "player_join").await;
+    assert_eq!(joined["payload"]["room"], "medium");
+    assert_eq!(app.balance(alice.user.id).await, 30.0);
+}
+
+#[actix_web::test]
+async fn spectators_can_not_deposit() {
+    let app = TestApp::spawn().await;
+    let mut spectator = app.ws("/ws/jackpot").await;
+
+    spectator
+        .send(json!({"action": "deposit", "amount": 5.0}))
+        .await;
+    let rejected = spectator
+        .expect(|m| m["variant"] == "deposit_rejected")
+        .await;
+    assert_eq!(rejected["msg"], "Log in to play");
+}